@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::path::Path;
+use std::process::Command;
+
+use crate::history::{self, Offender};
+use crate::project_config::ProjectConfig;
+use crate::rules::{CommitRules, CLAUDE_DIR_PREFIX};
+
+/// Scan commit history for `.claude/` files and forbidden patterns (same rules as the hooks)
+///
+/// `range` is anything `git log` accepts (default: HEAD). Exits with status 1 when
+/// offending commits are found, so it can be used in CI.
+pub fn commits(range: Option<&str>, show_fix: bool) -> Result<()> {
+    let project_dir = Path::new(".");
+    let config = ProjectConfig::load(project_dir)?;
+    let rules = CommitRules::new(&config.hooks)?;
+
+    let revision = range.unwrap_or("HEAD");
+    let offenders = history::scan(project_dir, &rules, &[revision])?;
+
+    println!("{} {}", "Auditing commits:".green(), revision);
+    if offenders.is_empty() {
+        println!("  ✅ {}", "No .claude/ files or forbidden patterns found".green());
+        return Ok(());
+    }
+
+    println!();
+    for offender in &offenders {
+        println!("  {} {}", offender.short().yellow(), offender.subject);
+        for path in &offender.claude_paths {
+            println!("    - adds {}", path);
+        }
+        for line in &offender.signatures {
+            println!("    - message: {}", line);
+        }
+    }
+
+    let with_paths = offenders.iter().filter(|o| !o.claude_paths.is_empty()).count();
+    let with_signatures = offenders.iter().filter(|o| !o.signatures.is_empty()).count();
+    println!();
+    println!(
+        "{} {} commit(s): {} with .claude/ files, {} with forbidden patterns",
+        "Found:".red(),
+        offenders.len(),
+        with_paths,
+        with_signatures
+    );
+
+    if show_fix {
+        print_fix(project_dir, &config, &offenders, range)?;
+    } else {
+        println!("  {}", "Run with --fix to show the commands to clean up the history".dimmed());
+    }
+    std::process::exit(1);
+}
+
+/// Print (never run) the commands that remove the offending content
+fn print_fix(project_dir: &Path, config: &ProjectConfig, offenders: &[Offender], range: Option<&str>) -> Result<()> {
+    let has_paths = offenders.iter().any(|o| !o.claude_paths.is_empty());
+    let has_signatures = offenders.iter().any(|o| !o.signatures.is_empty());
+    let refs = range.map(|r| format!(" --refs {}", shell_quote(r))).unwrap_or_default();
+
+    println!();
+    println!("{}", "How to clean up (not run; review before executing):".bold());
+
+    if has_paths && is_tracked(project_dir, CLAUDE_DIR_PREFIX)? {
+        println!();
+        println!("  # Stop tracking .claude/ (files stay on disk)");
+        println!("  git rm -r --cached {}", CLAUDE_DIR_PREFIX);
+        println!("  git commit -m \"Stop tracking .claude/\"");
+    }
+    if has_paths {
+        println!();
+        println!("  # Remove .claude/ from history (requires git-filter-repo)");
+        println!("  git filter-repo --invert-paths --path {}{}", CLAUDE_DIR_PREFIX, refs);
+    }
+    if has_signatures {
+        let callback = message_callback(&config.hooks.forbidden_patterns());
+        println!();
+        println!("  # Remove lines matching the forbidden patterns from commit messages");
+        println!("  git filter-repo --message-callback {}{}", shell_quote(&callback), refs);
+    }
+
+    println!();
+    println!("  {}", "filter-repo rewrites commit IDs: back up first, then force-push and".yellow());
+    println!("  {}", "ask collaborators to re-clone or rebase onto the rewritten branches.".yellow());
+    Ok(())
+}
+
+/// filter-repo `--message-callback` body removing the lines that match `patterns`
+fn message_callback(patterns: &[String]) -> String {
+    format!(
+        "import re; return re.sub(rb\"(?m)^.*(?:{}).*\\n?\", b\"\", message)",
+        patterns.join("|").replace('"', "\\\"")
+    )
+}
+
+/// Check if any file under `path` is in the index
+fn is_tracked(project_dir: &Path, path: &str) -> Result<bool> {
+    let output = Command::new("git")
+        .args(["ls-files", "--", path])
+        .current_dir(project_dir)
+        .output()
+        .context("Failed to run git")?;
+    Ok(output.status.success() && !output.stdout.is_empty())
+}
+
+/// Quote a string for a POSIX shell
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_callback_quoting() {
+        let patterns = vec![r"Co-Authored-By: Claude".to_string(), r#"it's "done""#.to_string()];
+        let callback = message_callback(&patterns);
+        assert_eq!(
+            callback,
+            r#"import re; return re.sub(rb"(?m)^.*(?:Co-Authored-By: Claude|it's \"done\").*\n?", b"", message)"#
+        );
+        assert_eq!(
+            shell_quote(&callback),
+            r#"'import re; return re.sub(rb"(?m)^.*(?:Co-Authored-By: Claude|it'\''s \"done\").*\n?", b"", message)'"#
+        );
+    }
+}
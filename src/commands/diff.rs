@@ -0,0 +1,288 @@
+use anyhow::{Context, Result};
+use colored::*;
+use similar::TextDiff;
+use std::collections::BTreeSet;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::keep;
+use crate::lock::{self, InstallLock};
+use crate::packs::{self, FetchPolicy};
+use crate::profiles::ActiveProfiles;
+use crate::render::{self, TemplateVars};
+use crate::templates::{Category, InstallFilter, TemplateOrigin, TemplateSet};
+
+/// Files hagi keeps next to the templates that are never reported as extra
+const INTERNAL_ENTRIES: &[&str] = &[lock::LOCK_FILE, lock::BASE_DIR];
+
+/// Result of comparing the embedded templates with `.claude/`
+#[derive(Default)]
+struct DiffReport {
+    identical: Vec<String>,
+    missing: Vec<String>,
+    extra: Vec<String>,
+    /// Installed files that are not UTF-8 text or cannot be read
+    unreadable: Vec<String>,
+    changed: usize,
+}
+
+/// Show a unified diff between the embedded templates and the installed `.claude/`
+///
+/// Identical files are only counted unless `list_identical` is set.
+pub fn diff(categories: Vec<Category>, paths: &[String], list_identical: bool) -> Result<()> {
+    let project_dir = env::current_dir().context("Failed to get current directory")?;
+    let claude_dir = project_dir.join(".claude");
+
+    if !claude_dir.exists() {
+        println!("{} .claude/ not found", "✗".red());
+        println!("\nRun {} to install project configuration", "hagi install".yellow());
+        return Ok(());
+    }
+
+    let install_lock = InstallLock::load(&claude_dir)?;
+    let resolved_packs = packs::resolve_packs(&[], &install_lock, FetchPolicy::Pinned)?;
+    let template_set = packs::build_template_set(&resolved_packs)?;
+    let profiles = ActiveProfiles::resolve(&project_dir, &install_lock.profiles)?;
+    let filter = InstallFilter::new(categories, Vec::new()).with_profiles(&profiles);
+    let mut vars = TemplateVars::derive(&profiles);
+    vars.extend(&install_lock.vars);
+
+    let mut output = String::new();
+    let mut report = DiffReport::default();
+
+    let template_files: Vec<_> = template_set
+        .files(&filter)
+        .into_iter()
+        .filter(|(path, _)| matches_paths(path, paths))
+        .collect();
+
+    for (relative_path, template) in &template_files {
+        let installed_path = claude_dir.join(relative_path);
+        let name = relative_path.display().to_string();
+
+        if !installed_path.exists() {
+            report.missing.push(name);
+            continue;
+        }
+
+        let Ok(Ok(installed)) = fs::read(&installed_path).map(String::from_utf8) else {
+            report.unreadable.push(name);
+            continue;
+        };
+        let rendered = render::render_file(relative_path, template, &vars)
+            .with_context(|| format!("Failed to render template: {}", name))?;
+        let rendered = profiles.apply(relative_path, rendered)?;
+        let expected = expected_content(&rendered, &installed);
+
+        if installed == expected {
+            report.identical.push(name);
+        } else {
+            report.changed += 1;
+            let source = match template_set.origin(relative_path) {
+                Some(TemplateOrigin::Pack(pack)) => pack.trim_end_matches('/'),
+                Some(TemplateOrigin::Overlay) => "overlay",
+                _ => "templates",
+            };
+            write_unified_diff(&mut output, &name, source, &installed, &expected);
+        }
+    }
+
+    let known: BTreeSet<PathBuf> = template_files.iter().map(|(p, _)| p.to_path_buf()).collect();
+    report.extra = find_extra_files(&claude_dir, &template_set, &install_lock, &known, &filter, paths)?;
+
+    write_summary(&mut output, &report, list_identical);
+    page_output(&output)
+}
+
+/// Content hagi would install for a rendered template, given what is installed now
+///
+/// `hagi:keep` blocks are carried over, exactly as `update_claude_md` and template copies do.
+fn expected_content(template: &str, installed: &str) -> String {
+    if keep::has_blocks(installed) {
+        return keep::apply(template, installed).text;
+    }
+    template.to_string()
+}
+
+/// Check if a template path is selected by the positional path arguments
+fn matches_paths(path: &Path, paths: &[String]) -> bool {
+    paths.is_empty() || paths.iter().any(|p| path.starts_with(p.trim_end_matches('/')))
+}
+
+/// Files that hagi owns (recorded in the install lock, or inside a template directory)
+/// but that the current templates no longer contain
+fn find_extra_files(
+    claude_dir: &Path,
+    template_set: &TemplateSet,
+    install_lock: &InstallLock,
+    known: &BTreeSet<PathBuf>,
+    filter: &InstallFilter,
+    paths: &[String],
+) -> Result<Vec<String>> {
+    let mut candidates: BTreeSet<PathBuf> = install_lock
+        .files
+        .keys()
+        .map(PathBuf::from)
+        .collect();
+
+    // Top-level directories that exist in the template tree (instructions/, skills/, ...)
+    let template_dirs: BTreeSet<PathBuf> = template_set
+        .files(&InstallFilter::default())
+        .iter()
+        .filter_map(|(p, _)| p.components().next().map(|c| PathBuf::from(c.as_os_str())))
+        .filter(|p| claude_dir.join(p).is_dir())
+        .collect();
+    for dir in &template_dirs {
+        collect_files(claude_dir, &claude_dir.join(dir), &mut candidates)?;
+    }
+
+    Ok(candidates
+        .into_iter()
+        .filter(|p| !known.contains(p))
+        .filter(|p| claude_dir.join(p).exists())
+        .filter(|p| !INTERNAL_ENTRIES.iter().any(|e| p.starts_with(e)))
+        .filter(|p| !p.to_string_lossy().contains(".backup."))
+        .filter(|p| !filter.has_only() || Category::for_path(p).is_some_and(|c| filter.includes_category(c)))
+        .filter(|p| matches_paths(p, paths))
+        .map(|p| p.display().to_string())
+        .collect())
+}
+
+/// Recursively collect files under `dir` as paths relative to `base`
+fn collect_files(base: &Path, dir: &Path, files: &mut BTreeSet<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(base, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(base) {
+            files.insert(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Append a coloured unified diff (installed → template) to `output`
+///
+/// `source` labels the template side (`templates` or the pack it came from).
+fn write_unified_diff(output: &mut String, name: &str, source: &str, installed: &str, expected: &str) {
+    let diff = TextDiff::from_lines(installed, expected);
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/.claude/{}", name), &format!("b/{}/{}", source, name))
+        .to_string();
+
+    for line in unified.lines() {
+        let colored_line = if line.starts_with("+++") || line.starts_with("---") {
+            line.bold()
+        } else if line.starts_with('+') {
+            line.green()
+        } else if line.starts_with('-') {
+            line.red()
+        } else if line.starts_with("@@") {
+            line.cyan()
+        } else {
+            line.normal()
+        };
+        let _ = writeln!(output, "{}", colored_line);
+    }
+}
+
+fn write_summary(output: &mut String, report: &DiffReport, list_identical: bool) {
+    let _ = writeln!(output, "\n{}", "[Summary]".cyan().bold());
+    let _ = writeln!(output, "  {} {} differ", "~".yellow(), report.changed);
+    let _ = writeln!(output, "  {} {} identical", "=".dimmed(), report.identical.len());
+
+    if list_identical {
+        for name in &report.identical {
+            let _ = writeln!(output, "  {} {} - {}", "=".dimmed(), name, "identical".dimmed());
+        }
+    }
+    for name in &report.missing {
+        let _ = writeln!(output, "  {} {} - {}", "+".green(), name, "missing (not installed)".dimmed());
+    }
+    for name in &report.extra {
+        let _ = writeln!(output, "  {} {} - {}", "-".red(), name, "extra (not in templates)".dimmed());
+    }
+    for name in &report.unreadable {
+        let _ = writeln!(output, "  {} {} - {}", "?".yellow(), name, "binary/unreadable (not compared)".dimmed());
+    }
+}
+
+/// Print output, through `$PAGER` (default: `less -FRX`) when stdout is a terminal
+fn page_output(output: &str) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        print!("{}", output);
+        return Ok(());
+    }
+
+    let pager = env::var("PAGER").unwrap_or_else(|_| "less -FRX".to_string());
+    let mut parts = pager.split_whitespace();
+    let Some(program) = parts.next() else {
+        print!("{}", output);
+        return Ok(());
+    };
+
+    let child = Command::new(program).args(parts).stdin(Stdio::piped()).spawn();
+    let Ok(mut child) = child else {
+        // Pager not available: fall back to plain output
+        print!("{}", output);
+        return Ok(());
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Ignore broken pipe when the user quits the pager early
+        let _ = stdin.write_all(output.as_bytes());
+    }
+    child.wait().context("Failed to wait for pager")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_paths() {
+        let path = Path::new("instructions/git-workflow.md");
+        assert!(matches_paths(path, &[]));
+        assert!(matches_paths(path, &["instructions/".to_string()]));
+        assert!(matches_paths(path, &["skills".to_string(), "instructions/git-workflow.md".to_string()]));
+        assert!(!matches_paths(path, &["instr".to_string()]));
+    }
+
+    #[test]
+    fn test_expected_content_keeps_blocks() {
+        let template = "A\n<!-- hagi:keep:notes:start -->\n(notes)\n<!-- hagi:keep:notes:end -->\n";
+        let installed = "old\n<!-- hagi:keep:notes:start -->\nmine\n<!-- hagi:keep:notes:end -->\n";
+        assert_eq!(expected_content(template, installed), template.replace("(notes)", "mine"));
+        assert_eq!(expected_content(template, "no blocks\n"), template);
+    }
+
+    #[test]
+    fn test_summary_lists_files() {
+        colored::control::set_override(false);
+        let report = DiffReport {
+            identical: vec!["skills/st/SKILL.md".to_string()],
+            missing: vec!["TODO.md".to_string()],
+            extra: vec!["instructions/old.md".to_string()],
+            unreadable: vec!["designs/logo.png".to_string()],
+            changed: 1,
+        };
+        let mut output = String::new();
+        write_summary(&mut output, &report, false);
+        assert!(output.contains("  = 1 identical\n"));
+        assert!(!output.contains("skills/st/SKILL.md"));
+        assert!(output.contains("  + TODO.md - missing (not installed)\n"));
+        assert!(output.contains("  - instructions/old.md - extra (not in templates)\n"));
+        assert!(output.contains("  ? designs/logo.png - binary/unreadable (not compared)\n"));
+
+        let mut output = String::new();
+        write_summary(&mut output, &report, true);
+        assert!(output.contains("  = skills/st/SKILL.md - identical\n"));
+    }
+}
@@ -0,0 +1,207 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use std::env;
+use std::path::Path;
+
+use crate::project_config::ProjectConfig;
+use crate::rules::CommitRules;
+use crate::utils;
+
+/// Paths hagi manages outside git (`git ls-files` pathspecs)
+pub const UNTRACK_PATHS: &[&str] = &[".claude", ".mcp.json"];
+
+/// Branch created for the untrack commit when the current branch is protected
+const DEFAULT_UNTRACK_BRANCH: &str = "chore/untrack-claude";
+
+/// Default message of the untrack commit
+const DEFAULT_UNTRACK_MESSAGE: &str = "🔧 Stop tracking .claude/ and .mcp.json";
+
+/// Options for `hagi git untrack`
+#[derive(Debug, Default)]
+pub struct UntrackOptions {
+    pub dry_run: bool,
+    pub yes: bool,
+    /// Branch to create when the current branch is protected
+    pub branch: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Files under `.claude/` and `.mcp.json` that are in the git index
+///
+/// Returns an empty list outside a git repository.
+pub fn tracked_files(project_dir: &Path) -> Vec<String> {
+    let mut args = vec!["ls-files", "--"];
+    args.extend_from_slice(UNTRACK_PATHS);
+    utils::git_output(project_dir, &args)
+        .map(|files| files.lines().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Warn about tracked `.claude/` files (ignoring them in .gitignore has no effect)
+pub fn warn_tracked(project_dir: &Path) {
+    let files = tracked_files(project_dir);
+    if files.is_empty() {
+        return;
+    }
+
+    println!(
+        "\n{} {} file(s) under .claude/ or .mcp.json are tracked by git; .gitignore does not apply to them:",
+        "⚠".yellow(),
+        files.len()
+    );
+    for file in files.iter().take(5) {
+        println!("    {}", file);
+    }
+    if files.len() > 5 {
+        println!("    {}", format!("... and {} more", files.len() - 5).dimmed());
+    }
+    println!(
+        "  Run {} to remove them from the index (files stay on disk)",
+        "hagi git untrack".yellow()
+    );
+}
+
+/// Remove `.claude/` and `.mcp.json` from the index (never from disk) and commit
+///
+/// The commit follows the pre-commit hook's branch rules: on a protected branch a
+/// new branch is created first.
+pub fn untrack(opts: &UntrackOptions) -> Result<()> {
+    let project_dir = env::current_dir().context("Failed to get current directory")?;
+    untrack_in(&project_dir, opts)
+}
+
+fn untrack_in(project_dir: &Path, opts: &UntrackOptions) -> Result<()> {
+    utils::git(project_dir, &["rev-parse", "--git-dir"]).context("Not a git repository")?;
+
+    let files = tracked_files(project_dir);
+    if files.is_empty() {
+        println!("{}", "✓ No tracked .claude/ or .mcp.json files".green());
+        return Ok(());
+    }
+    if utils::git(project_dir, &["diff", "--cached", "--quiet"]).is_err() {
+        bail!("There are staged changes; commit or unstage them first so the untrack commit contains only the removal");
+    }
+
+    let config = ProjectConfig::load(project_dir)?;
+    let rules = CommitRules::new(&config.hooks)?;
+    let current = utils::git(project_dir, &["branch", "--show-current"]).unwrap_or_default();
+    let has_head = utils::git(project_dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok();
+    let new_branch = if has_head && !current.is_empty() && rules.is_protected(&current) {
+        let branch = opts.branch.clone().unwrap_or_else(|| DEFAULT_UNTRACK_BRANCH.to_string());
+        if rules.is_protected(&branch) {
+            bail!("Branch '{}' is protected; choose another with --branch", branch);
+        }
+        Some(branch)
+    } else {
+        None
+    };
+    let message = opts.message.as_deref().unwrap_or(DEFAULT_UNTRACK_MESSAGE);
+
+    println!("{} {} file(s)", "Untracking:".green(), files.len());
+    for file in &files {
+        println!("  {}", file);
+    }
+    if let Some(branch) = &new_branch {
+        println!("{} '{}' is protected; the commit goes to a new branch '{}'", "ℹ".blue(), current, branch);
+    }
+    println!("{} {}", "Commit:".green(), message);
+
+    if opts.dry_run {
+        println!();
+        if let Some(branch) = &new_branch {
+            println!("{} git switch -c {}", "Would run:".yellow(), branch);
+        }
+        println!("{} git rm -r --cached -- {}", "Would run:".yellow(), UNTRACK_PATHS.join(" "));
+        println!("{} git commit -m \"{}\"", "Would run:".yellow(), message);
+        return Ok(());
+    }
+    if !opts.yes && !utils::confirm("Remove these files from the index and commit?")? {
+        println!("{}", "Cancelled".yellow());
+        return Ok(());
+    }
+
+    if let Some(branch) = &new_branch {
+        utils::git(project_dir, &["switch", "-c", branch])?;
+        println!("  {} {}", "Switched to new branch:".green(), branch);
+    }
+    let mut rm_args = vec!["rm", "-r", "--cached", "--quiet", "--ignore-unmatch", "--"];
+    rm_args.extend_from_slice(UNTRACK_PATHS);
+    utils::git(project_dir, &rm_args)?;
+    utils::git(project_dir, &["commit", "-m", message])?;
+    println!("  ✅ {}", "Removed from the index and committed (files kept on disk)".green());
+
+    if utils::git(project_dir, &["check-ignore", "--quiet", ".claude/"]).is_err() {
+        println!(
+            "  {} .claude/ is not in .gitignore; run {} to add it",
+            "⚠".yellow(),
+            "hagi install".yellow()
+        );
+    }
+    if let Some(branch) = &new_branch {
+        println!("  {}", format!("Merge '{}' into '{}' via your usual review flow", branch, current).dimmed());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn init_repo(dir: &Path) {
+        utils::init_test_repo(dir);
+        fs::create_dir(dir.join(".claude")).unwrap();
+        fs::write(dir.join(".claude/settings.json"), "{}").unwrap();
+        fs::write(dir.join("README.md"), "readme").unwrap();
+        utils::git(dir, &["add", "-A"]).unwrap();
+        utils::git(dir, &["commit", "-qm", "Add files"]).unwrap();
+    }
+
+    #[test]
+    fn test_untrack_commits_to_new_branch_on_protected() {
+        let repo = tempfile::tempdir().unwrap();
+        let dir = repo.path();
+        init_repo(dir);
+
+        let dry_run = UntrackOptions { dry_run: true, ..Default::default() };
+        untrack_in(dir, &dry_run).unwrap();
+        assert_eq!(utils::git(dir, &["branch", "--show-current"]).unwrap(), "main");
+        assert_eq!(tracked_files(dir), vec![".claude/settings.json"]);
+
+        let opts = UntrackOptions { yes: true, ..Default::default() };
+        untrack_in(dir, &opts).unwrap();
+        assert_eq!(utils::git(dir, &["branch", "--show-current"]).unwrap(), DEFAULT_UNTRACK_BRANCH);
+        assert!(tracked_files(dir).is_empty());
+        assert!(dir.join(".claude/settings.json").exists());
+        assert_eq!(utils::git(dir, &["log", "-1", "--format=%s"]).unwrap(), DEFAULT_UNTRACK_MESSAGE);
+        assert_eq!(
+            utils::git(dir, &["ls-tree", "-r", "--name-only", "main"]).unwrap(),
+            ".claude/settings.json\nREADME.md"
+        );
+    }
+
+    #[test]
+    fn test_untrack_stays_on_unprotected_branch() {
+        let repo = tempfile::tempdir().unwrap();
+        let dir = repo.path();
+        init_repo(dir);
+        utils::git(dir, &["switch", "-qc", "feature/x"]).unwrap();
+
+        let opts = UntrackOptions { yes: true, ..Default::default() };
+        untrack_in(dir, &opts).unwrap();
+        assert_eq!(utils::git(dir, &["branch", "--show-current"]).unwrap(), "feature/x");
+        assert!(tracked_files(dir).is_empty());
+        assert!(dir.join(".claude/settings.json").exists());
+    }
+
+    #[test]
+    fn test_untrack_rejects_protected_target_branch() {
+        let repo = tempfile::tempdir().unwrap();
+        let dir = repo.path();
+        init_repo(dir);
+
+        let opts = UntrackOptions { yes: true, branch: Some("master".to_string()), ..Default::default() };
+        assert!(untrack_in(dir, &opts).is_err());
+        assert_eq!(tracked_files(dir), vec![".claude/settings.json"]);
+    }
+}
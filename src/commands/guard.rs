@@ -0,0 +1,57 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::env;
+use std::io::{self, Read};
+
+use crate::guard::{Action, GuardRules, HookPayload};
+
+/// Claude Code PreToolUse hook: read the tool call from stdin and print a decision
+///
+/// Prints nothing when no rule applies, leaving the call to the normal permission flow.
+pub fn pretooluse() -> Result<()> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .context("Failed to read hook input from stdin")?;
+    let payload = HookPayload::parse(&input)?;
+
+    let project_dir = env::current_dir().context("Failed to get current directory")?;
+    let rules = GuardRules::load(&project_dir)?;
+    if let Some(decision) = rules.evaluate(&payload) {
+        println!("{}", decision.to_hook_output());
+    }
+    Ok(())
+}
+
+/// Evaluate a hook payload and show which rule fired (`-` reads the payload from stdin)
+pub fn test(payload: &str) -> Result<()> {
+    let input = if payload == "-" {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .context("Failed to read hook input from stdin")?;
+        input
+    } else {
+        payload.to_string()
+    };
+    let payload = HookPayload::parse(&input)?;
+
+    let project_dir = env::current_dir().context("Failed to get current directory")?;
+    let rules = GuardRules::load(&project_dir)?;
+
+    let Some(decision) = rules.evaluate(&payload) else {
+        println!("{} no rule matched (no decision, normal permission flow)", "–".dimmed());
+        return Ok(());
+    };
+
+    let action = match decision.action {
+        Action::Deny => decision.action.to_string().red().bold(),
+        Action::Ask => decision.action.to_string().yellow().bold(),
+        Action::Allow => decision.action.to_string().green().bold(),
+    };
+    println!("{} {}", "Rule:".cyan(), decision.rule);
+    println!("{} {}", "Action:".cyan(), action);
+    println!("{} {}", "Reason:".cyan(), decision.reason);
+    println!("{} {}", "Output:".cyan(), decision.to_hook_output().to_string().dimmed());
+    Ok(())
+}
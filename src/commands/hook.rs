@@ -0,0 +1,318 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+use crate::history::{self, Offender};
+use crate::project_config::ProjectConfig;
+use crate::rules::{self, CommitRules, MessageViolation};
+use crate::utils;
+
+/// Git hooks `hagi hook run` implements
+pub const HOOK_NAMES: &[&str] = &["pre-commit", "commit-msg", "pre-push", "prepare-commit-msg"];
+
+/// Run the checks for a git hook (called by the installed hook shims)
+///
+/// Exits with status 1 when a check fails so that git aborts the operation.
+pub fn run(name: &str, args: &[String]) -> Result<()> {
+    let project_dir = Path::new(".");
+    let config = ProjectConfig::load(project_dir)?;
+    let rules = CommitRules::new(&config.hooks)?;
+
+    let passed = match name {
+        "pre-commit" => pre_commit(project_dir, &rules)?,
+        "commit-msg" => {
+            let Some(message_file) = args.first() else {
+                bail!("commit-msg requires the commit message file as an argument");
+            };
+            commit_msg(project_dir, &rules, Path::new(message_file))?
+        }
+        "pre-push" => pre_push(project_dir, &rules)?,
+        "prepare-commit-msg" => {
+            let Some(message_file) = args.first() else {
+                bail!("prepare-commit-msg requires the commit message file as an argument");
+            };
+            prepare_commit_msg(project_dir, &rules, Path::new(message_file))?
+        }
+        _ => bail!("Unknown hook: '{}'\nValid hooks: {}", name, HOOK_NAMES.join(", ")),
+    };
+
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// ============================================================================
+// pre-commit
+// ============================================================================
+
+/// Block staged `.claude/` files and direct commits to protected branches
+fn pre_commit(project_dir: &Path, rules: &CommitRules) -> Result<bool> {
+    // Deletions are allowed so that tracked .claude/ files can be removed from the index
+    let staged = utils::git(project_dir, &["diff", "--cached", "--name-only", "--diff-filter=d"])?;
+    let claude_files: Vec<&str> = staged.lines().filter(|p| rules::is_claude_path(p)).collect();
+    if !claude_files.is_empty() {
+        eprintln!();
+        eprintln!("{}", "❌ ERROR: .claude/ files should not be committed!".red());
+        eprintln!();
+        for file in &claude_files {
+            eprintln!("  {}", file);
+        }
+        eprintln!();
+        eprintln!("📖 See: .claude/instructions/git-workflow.md");
+        eprintln!("   Section: '.claude/ Directory is OUTSIDE Git Workflow'");
+        eprintln!();
+        eprintln!(".claude/ is managed separately via 'hagi sync', not git.");
+        eprintln!("Your edits are already saved locally. No commit needed.");
+        eprintln!();
+        eprintln!("To unstage these files:");
+        eprintln!("  git restore --staged .claude/");
+        eprintln!();
+        return Ok(false);
+    }
+
+    // Skip branch protection for the initial commit (no HEAD yet) and detached HEAD
+    if utils::git(project_dir, &["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+        return Ok(true);
+    }
+    let branch = utils::git(project_dir, &["branch", "--show-current"])?;
+    if !branch.is_empty() && rules.is_protected(&branch) {
+        eprintln!("{}", format!("❌ ERROR: Direct commits to '{}' branch are not allowed!", branch).red());
+        eprintln!();
+        eprintln!("Please create a feature branch first:");
+        eprintln!("  git checkout -b feature/your-feature-name");
+        eprintln!();
+        eprintln!("Or move your changes to a new branch:");
+        eprintln!("  git checkout -b feature/your-feature-name");
+        eprintln!("  git branch -D {}  # (after confirming the new branch)", branch);
+        eprintln!();
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+// ============================================================================
+// commit-msg
+// ============================================================================
+
+/// Reject signatures, forbidden patterns and badly formed subjects
+fn commit_msg(project_dir: &Path, rules: &CommitRules, message_file: &Path) -> Result<bool> {
+    let raw = fs::read_to_string(message_file)
+        .with_context(|| format!("Failed to read commit message: {}", message_file.display()))?;
+    let message = rules::clean_message(&raw, &comment_char(project_dir));
+
+    let violations = rules.check_message(&message);
+    if violations.is_empty() {
+        return Ok(true);
+    }
+
+    eprintln!();
+    for violation in &violations {
+        print_violation(violation);
+    }
+    if violations.iter().any(|v| matches!(v, MessageViolation::Forbidden { .. })) {
+        eprintln!();
+        eprintln!("Please remove lines such as:");
+        eprintln!("  🤖 Generated with [Claude Code](https://claude.com/claude-code)");
+        eprintln!("  Co-Authored-By: Claude <noreply@anthropic.com>");
+        eprintln!();
+        eprintln!("Commit messages should be clean and professional.");
+    }
+    eprintln!();
+    Ok(false)
+}
+
+/// Comment character git uses in the message file (`core.commentChar`)
+fn comment_char(project_dir: &Path) -> String {
+    match utils::git(project_dir, &["config", "--get", "core.commentChar"]) {
+        Ok(c) if !c.is_empty() && c != "auto" => c,
+        _ => "#".to_string(),
+    }
+}
+
+fn print_violation(violation: &MessageViolation) {
+    match violation {
+        MessageViolation::Forbidden { pattern, line } => {
+            eprintln!("{}", format!("❌ ERROR: Commit message matches a forbidden pattern: {}", pattern).red());
+            eprintln!("  line: {}", line);
+        }
+        MessageViolation::SubjectFormat { pattern } => {
+            eprintln!("{}", "❌ ERROR: Commit subject does not match the required format".red());
+            eprintln!("  pattern: {}", pattern);
+        }
+        MessageViolation::SubjectLength { max, actual } => {
+            eprintln!(
+                "{}",
+                format!("❌ ERROR: Commit subject is longer than {} characters ({})", max, actual).red()
+            );
+        }
+    }
+}
+
+// ============================================================================
+// prepare-commit-msg
+// ============================================================================
+
+/// Strip signature lines from the message before the editor opens (message mode: strip)
+///
+/// Never blocks the commit; commit-msg still checks the final message.
+fn prepare_commit_msg(project_dir: &Path, rules: &CommitRules, message_file: &Path) -> Result<bool> {
+    let raw = fs::read_to_string(message_file)
+        .with_context(|| format!("Failed to read commit message: {}", message_file.display()))?;
+    let (stripped, removed) = rules.strip_signatures(&raw, &comment_char(project_dir));
+    if removed.is_empty() {
+        return Ok(true);
+    }
+
+    fs::write(message_file, stripped)
+        .with_context(|| format!("Failed to write commit message: {}", message_file.display()))?;
+    eprintln!("{}", "✂ hagi: removed from the commit message:".yellow());
+    for line in &removed {
+        eprintln!("  {}", line);
+    }
+    Ok(true)
+}
+
+// ============================================================================
+// pre-push
+// ============================================================================
+
+/// Block pushing commits that add `.claude/` files or carry forbidden patterns
+///
+/// Reads `<local ref> <local sha> <remote ref> <remote sha>` lines from stdin. Under the
+/// pre-commit framework (no stdin) the range comes from `PRE_COMMIT_FROM_REF`/`PRE_COMMIT_TO_REF`.
+fn pre_push(project_dir: &Path, rules: &CommitRules) -> Result<bool> {
+    let ranges = if let (Ok(from), Ok(to)) = (env::var("PRE_COMMIT_FROM_REF"), env::var("PRE_COMMIT_TO_REF")) {
+        vec![(to.clone(), vec![format!("{}..{}", from, to)])]
+    } else {
+        push_ranges(io::stdin().lock(), |sha| {
+            utils::git(project_dir, &["cat-file", "-e", &format!("{}^{{commit}}", sha)]).is_ok()
+        })?
+    };
+
+    let mut offenders = Vec::new();
+    let mut tips = Vec::new();
+    for (tip, revisions) in &ranges {
+        let revisions: Vec<&str> = revisions.iter().map(String::as_str).collect();
+        for offender in history::scan(project_dir, rules, &revisions)? {
+            if !offenders.iter().any(|o: &Offender| o.commit == offender.commit) {
+                offenders.push(offender);
+            }
+        }
+        tips.push(tip.as_str());
+    }
+
+    if offenders.is_empty() {
+        return Ok(true);
+    }
+
+    eprintln!();
+    eprintln!("{}", "❌ ERROR: Push blocked: commits contain .claude/ files or forbidden signatures".red());
+    eprintln!();
+    for offender in &offenders {
+        eprintln!("  {} {}", offender.short().yellow(), offender.subject);
+        for path in &offender.claude_paths {
+            eprintln!("    - adds {}", path);
+        }
+        for line in &offender.signatures {
+            eprintln!("    - message: {}", line);
+        }
+    }
+    print_push_fixes(&offenders, &tips);
+    Ok(false)
+}
+
+/// Revisions to scan for each pushed ref: `(local sha, git log revisions)`
+///
+/// Deletions are skipped. A new branch, or a remote sha that is not in the local
+/// repository (force push without a fetch), is scanned as the commits on no remote.
+fn push_ranges(input: impl BufRead, has_commit: impl Fn(&str) -> bool) -> Result<Vec<(String, Vec<String>)>> {
+    let mut ranges = Vec::new();
+    for line in input.lines() {
+        let line = line.context("Failed to read pre-push input")?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [_, local_sha, _, remote_sha] = fields[..] else {
+            continue;
+        };
+        if is_null_sha(local_sha) {
+            continue; // branch deletion
+        }
+        let revisions = if is_null_sha(remote_sha) || !has_commit(remote_sha) {
+            // Commits not on any remote yet
+            vec![local_sha.to_string(), "--not".to_string(), "--remotes".to_string()]
+        } else {
+            vec![format!("{}..{}", remote_sha, local_sha)]
+        };
+        ranges.push((local_sha.to_string(), revisions));
+    }
+    Ok(ranges)
+}
+
+/// Whether a pre-push sha is git's all-zero "no object" name (SHA-1 or SHA-256)
+fn is_null_sha(sha: &str) -> bool {
+    !sha.is_empty() && sha.bytes().all(|b| b == b'0')
+}
+
+/// Explain how to rewrite the offending commits before pushing again
+fn print_push_fixes(offenders: &[Offender], tips: &[&str]) {
+    let only_tip = offenders.len() == 1 && tips.contains(&offenders[0].commit.as_str());
+    let has_paths = offenders.iter().any(|o| !o.claude_paths.is_empty());
+    let has_signatures = offenders.iter().any(|o| !o.signatures.is_empty());
+
+    eprintln!();
+    eprintln!("How to fix:");
+    if only_tip {
+        if has_paths {
+            eprintln!("  git rm -r --cached .claude/ && git commit --amend --no-edit");
+        }
+        if has_signatures {
+            eprintln!("  git commit --amend    # remove the signature lines from the message");
+        }
+    } else {
+        let oldest = offenders.last().map(Offender::short).unwrap_or_default();
+        eprintln!("  git rebase -i {}^", oldest);
+        if has_paths {
+            eprintln!("    mark the commits 'edit', then for each: git rm -r --cached .claude/ && git commit --amend --no-edit && git rebase --continue");
+        }
+        if has_signatures {
+            eprintln!("    mark the commits 'reword' and remove the signature lines");
+        }
+    }
+    eprintln!();
+    eprintln!("Then push again.");
+    eprintln!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NULL: &str = "0000000000000000000000000000000000000000";
+
+    #[test]
+    fn test_push_ranges() {
+        let input = format!(
+            "refs/heads/gone {NULL} refs/heads/gone aaaa\n\
+             refs/heads/new bbbb refs/heads/new {NULL}\n\
+             refs/heads/main cccc refs/heads/main dddd\n\
+             refs/heads/forced eeee refs/heads/forced ffff\n\
+             \n"
+        );
+        let ranges = push_ranges(input.as_bytes(), |sha| sha != "ffff").unwrap();
+        let not_on_remotes = |sha: &str| vec![sha.to_string(), "--not".to_string(), "--remotes".to_string()];
+        assert_eq!(
+            ranges,
+            vec![
+                // deletion skipped; new branch: commits on no remote
+                ("bbbb".to_string(), not_on_remotes("bbbb")),
+                ("cccc".to_string(), vec!["dddd..cccc".to_string()]),
+                // remote sha not fetched: same as a new branch instead of a git error
+                ("eeee".to_string(), not_on_remotes("eeee")),
+            ]
+        );
+        assert!(push_ranges("".as_bytes(), |_| true).unwrap().is_empty());
+    }
+}
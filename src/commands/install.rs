@@ -1,13 +1,32 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use colored::*;
+use std::collections::BTreeMap;
 use std::env;
+use std::fs;
 #[cfg(unix)]
 use std::os::unix::fs::symlink;
 use std::path::Path;
 use std::process::Command;
 
-use crate::templates::{self, Category, InstallFilter};
+use crate::commands;
+use crate::githooks;
+use crate::guard;
+use crate::ignore::{self, IgnoreMode};
+use crate::json_merge::MergeStrategy;
+use crate::lock::{self, InstallLock};
+use crate::packs::{self, FetchPolicy};
+use crate::profiles::{ActiveProfiles, Selection};
+use crate::project_config::ProjectConfig;
+use crate::render::TemplateVars;
+use crate::templates::{self, Category, InstallFilter, TemplateSet};
 use crate::utils;
+use crate::worktree;
+
+/// Global settings file (merged from the `settings.local.json` template)
+pub const GLOBAL_SETTINGS: &str = "settings.json";
+
+/// Target of the project `.mcp.json` symlink
+pub const MCP_SYMLINK_TARGET: &str = ".claude/mcp.json";
 
 // ============================================================================
 // Common Helpers
@@ -60,15 +79,30 @@ fn skip_claude_md_filter(filter: &InstallFilter, needs_separate_update: bool) ->
     if !needs_separate_update {
         return filter.clone();
     }
-    let mut extended_skip = filter.skip.clone();
-    extended_skip.push(templates::CLAUDE_MD.to_string());
-    templates::InstallFilter::new(filter.only.clone(), extended_skip)
+    let mut copy_filter = filter.clone();
+    copy_filter.skip.push(templates::CLAUDE_MD.to_string());
+    copy_filter
 }
 
-/// Install CLAUDE.md via smart update (preserve project section)
-fn install_claude_md(claude_dir: &Path, dry_run: bool) -> Result<()> {
+/// Install CLAUDE.md via smart update (carry its hagi:keep blocks)
+fn install_claude_md(
+    claude_dir: &Path,
+    templates: &TemplateSet,
+    dry_run: bool,
+    lock: &mut InstallLock,
+    vars: &TemplateVars,
+    adopt: bool,
+) -> Result<()> {
     let claude_md = claude_dir.join(templates::CLAUDE_MD);
-    templates::update_claude_md(&claude_md, dry_run)?;
+    templates::update_claude_md(&claude_md, templates, dry_run, lock, vars, adopt)?;
+    Ok(())
+}
+
+/// Save the install lock (skipped in dry run mode)
+fn save_install_lock(lock: &InstallLock, target_dir: &Path, dry_run: bool) -> Result<()> {
+    if !dry_run {
+        lock.save(target_dir)?;
+    }
     Ok(())
 }
 
@@ -76,35 +110,98 @@ fn install_claude_md(claude_dir: &Path, dry_run: bool) -> Result<()> {
 // Global Install
 // ============================================================================
 
+/// Options for `hagi install --global`
+#[derive(Debug, Default)]
+pub struct GlobalOptions {
+    /// How settings.json with local changes is merged (`--merge-strategy`)
+    pub merge_strategy: Option<MergeStrategy>,
+    /// Wrap an unmarked CLAUDE.md's custom content into a preserved block (`--adopt`)
+    pub adopt: bool,
+    /// Move git packs to the latest commit instead of the pinned one (`--update-packs`)
+    pub update_packs: bool,
+}
+
 /// Install global configuration to ~/.claude/
 ///
-/// Only installs settings.json (permissions, hooks, etc.)
-/// MCP configuration is handled per-project via .mcp.json symlink.
-pub fn install_global(dry_run: bool) -> Result<()> {
+/// Merges settings.json (permissions, hooks) and installs the user-level CLAUDE.md,
+/// instructions, skills and agents, filtered like a project install. The packs from
+/// `config.toml` and the user overlay are layered as for a project; CLAUDE.md keeps
+/// its hagi:keep blocks. MCP configuration is handled per-project via .mcp.json symlink.
+pub fn install_global(dry_run: bool, filter: &InstallFilter, opts: &GlobalOptions) -> Result<()> {
+    let merge_strategy = opts.merge_strategy;
     print_dry_run_header(dry_run);
     println!("{}", "Installing global configuration...".green());
 
+    if let Some(category) = filter.only.iter().find(|c| !Category::GLOBAL.contains(c)) {
+        let valid: Vec<&str> = Category::GLOBAL.iter().map(|c| c.as_str()).collect();
+        bail!(
+            "Category '{}' cannot be installed with --global\nValid categories: {}",
+            category,
+            valid.join(", ")
+        );
+    }
+
     print_dependency_warnings();
+    print_skip_list(&filter.skip);
 
     let claude_dir = utils::claude_dir()?;
     ensure_directory(&claude_dir, dry_run)?;
 
-    // Only install settings.json (Claude Code reads ~/.claude/settings.json)
-    install_settings(&claude_dir, dry_run)?;
+    let mut lock = InstallLock::load(&claude_dir)?;
+    let policy = if opts.update_packs { FetchPolicy::Update } else { FetchPolicy::Pinned };
+    let (resolved_packs, template_set) = global_template_set(&lock, policy)?;
+    packs::print_packs(&resolved_packs);
+    lock.packs = packs::pins(&resolved_packs);
+
+    // Claude Code reads ~/.claude/settings.json
+    if filter.includes_category(Category::Config) && !filter.skip.iter().any(|s| s == GLOBAL_SETTINGS) {
+        migrate_guard_settings(&claude_dir.join(GLOBAL_SETTINGS), dry_run)?;
+        install_settings(&claude_dir, &template_set, dry_run, merge_strategy, &mut lock)?;
+    }
+
+    // No project to detect profiles or derive variables from
+    let profiles = ActiveProfiles::user_level()?;
+    let vars = TemplateVars::default();
+    let filter = filter.clone().with_profiles(&profiles);
+    let copy_opts = templates::CopyOptions { dry_run, vars: &vars, profiles: &profiles, merge_strategy };
+
+    let needs_claude_md = filter.includes_category(Category::Docs)
+        && !filter.skip.iter().any(|s| s == templates::CLAUDE_MD);
+    let mut copy_filter = skip_claude_md_filter(&filter, needs_claude_md);
+    // Merged into settings.json above, never copied as is
+    copy_filter.exclude.push(templates::SETTINGS_TEMPLATE.to_string());
+    templates::copy_all_templates_filtered(&claude_dir, &template_set, &copy_filter, &mut lock, &copy_opts)?;
+    if needs_claude_md {
+        install_claude_md(&claude_dir, &template_set, dry_run, &mut lock, &vars, opts.adopt)?;
+    }
+
+    save_install_lock(&lock, &claude_dir, dry_run)?;
 
     if dry_run {
         print_dry_run_footer(dry_run);
     } else {
         println!("{}", "\n✅ Global configuration installed successfully!".green().bold());
         println!("\nInstalled:");
-        println!("  - ~/.claude/settings.json (permissions, hooks)");
+        for (category, count) in lock.count_by_category() {
+            println!("  - {}: {} file(s)", category, count);
+        }
         println!("\nNext steps:");
+        println!("  Write your own preferences between the hagi:keep:personal markers in ~/.claude/CLAUDE.md");
         println!("  Run 'hagi install' in your project directory for MCP configuration");
     }
 
     Ok(())
 }
 
+/// Packs for `~/.claude` (config.toml, pinned by `lock`) and the global template set built from them
+///
+/// Shared by install and uninstall so that both compare against the same templates.
+pub fn global_template_set(lock: &InstallLock, policy: FetchPolicy) -> Result<(Vec<packs::ResolvedPack>, TemplateSet)> {
+    let resolved_packs = packs::resolve_packs(&[], lock, policy)?;
+    let template_set = packs::build_template_set(&resolved_packs)?.into_global();
+    Ok((resolved_packs, template_set))
+}
+
 /// Check and print dependency warnings
 fn print_dependency_warnings() {
     let warnings = check_dependencies();
@@ -121,8 +218,34 @@ fn print_dependency_warnings() {
 // Project Install
 // ============================================================================
 
+/// Project install settings given on the command line
+#[derive(Debug, Default)]
+pub struct ProjectOptions {
+    /// Template variables (`--var`), overriding derived values
+    pub vars: BTreeMap<String, String>,
+    /// Profiles (`--profile`); detected from project files when empty
+    pub profiles: Vec<String>,
+    /// Template packs (`--templates`), layered over the embedded templates
+    pub packs: Vec<String>,
+    /// Move git packs to the latest commit instead of the pinned one (`--update-packs`)
+    pub update_packs: bool,
+    /// Optional git hooks (`--pre-push`)
+    pub hooks: githooks::HookOptions,
+    /// Where the ignore entries go (`--ignore-mode`); remembered in the install lock
+    pub ignore_mode: Option<IgnoreMode>,
+    /// In a linked worktree, share the main worktree's .claude/ via a symlink (`--shared`)
+    pub shared: bool,
+    /// How JSON configs with local changes are merged (`--merge-strategy`)
+    pub merge_strategy: Option<MergeStrategy>,
+    /// Wrap an unmarked CLAUDE.md's custom content into a preserved block (`--adopt`)
+    pub adopt: bool,
+}
+
 /// Install project-specific configuration to .claude/
-pub fn install_project(dry_run: bool, filter: &InstallFilter) -> Result<()> {
+///
+/// Variables, profiles and packs given on the command line are remembered in the
+/// install lock for later installs.
+pub fn install_project(dry_run: bool, filter: &InstallFilter, opts: &ProjectOptions) -> Result<()> {
     print_dry_run_header(dry_run);
 
     let selective = filter.has_only();
@@ -140,6 +263,36 @@ pub fn install_project(dry_run: bool, filter: &InstallFilter) -> Result<()> {
 
     let project_dir = env::current_dir().context("Failed to get current directory")?;
     let claude_dir = project_dir.join(".claude");
+    // Link before loading the lock so a shared install continues the main worktree's lock
+    if opts.shared {
+        worktree::link_shared(&project_dir, dry_run)?;
+    } else {
+        worktree::print_shared_hint(&project_dir);
+    }
+    let mut lock = InstallLock::load(&claude_dir)?;
+
+    if !opts.profiles.is_empty() {
+        lock.profiles = opts.profiles.clone();
+    }
+    let profiles = ActiveProfiles::resolve(&project_dir, &lock.profiles)?;
+    print_profiles(&profiles);
+    let filter = &filter.clone().with_profiles(&profiles);
+
+    let policy = if opts.update_packs { FetchPolicy::Update } else { FetchPolicy::Pinned };
+    let resolved_packs = packs::resolve_packs(&opts.packs, &lock, policy)?;
+    packs::print_packs(&resolved_packs);
+    let template_set = packs::build_template_set(&resolved_packs)?;
+    lock.packs = packs::pins(&resolved_packs);
+
+    lock.vars.extend(opts.vars.clone());
+    let mut vars = TemplateVars::derive(&profiles);
+    vars.extend(&lock.vars);
+    let copy_opts = templates::CopyOptions {
+        dry_run,
+        vars: &vars,
+        profiles: &profiles,
+        merge_strategy: opts.merge_strategy,
+    };
 
     if selective {
         // --only mode: only copy filtered templates
@@ -149,12 +302,14 @@ pub fn install_project(dry_run: bool, filter: &InstallFilter) -> Result<()> {
             || filter.includes_category(Category::Instructions);
         let copy_filter = skip_claude_md_filter(filter, needs_claude_md);
 
-        templates::copy_all_templates_filtered(&claude_dir, dry_run, &copy_filter)?;
+        templates::copy_all_templates_filtered(&claude_dir, &template_set, &copy_filter, &mut lock, &copy_opts)?;
 
         if needs_claude_md {
-            install_claude_md(&claude_dir, dry_run)?;
+            install_claude_md(&claude_dir, &template_set, dry_run, &mut lock, &vars, opts.adopt)?;
         }
 
+        save_install_lock(&lock, &claude_dir, dry_run)?;
+
         print_dry_run_footer(dry_run);
         if !dry_run {
             println!("{}", "\n✅ Selected categories installed successfully!".green().bold());
@@ -172,22 +327,28 @@ pub fn install_project(dry_run: bool, filter: &InstallFilter) -> Result<()> {
         ensure_directory(&claude_dir, dry_run)?;
 
         migrate_commands_to_skills(&claude_dir, dry_run)?;
+        migrate_guard_settings(&claude_dir.join(templates::SETTINGS_TEMPLATE), dry_run)?;
 
         let needs_claude_md = !filter.skip.iter().any(|s| s == templates::CLAUDE_MD);
         let copy_filter = skip_claude_md_filter(filter, needs_claude_md);
 
-        templates::copy_all_templates_filtered(&claude_dir, dry_run, &copy_filter)?;
+        templates::copy_all_templates_filtered(&claude_dir, &template_set, &copy_filter, &mut lock, &copy_opts)?;
 
         if needs_claude_md {
-            install_claude_md(&claude_dir, dry_run)?;
+            install_claude_md(&claude_dir, &template_set, dry_run, &mut lock, &vars, opts.adopt)?;
         }
 
+        let ignore_mode = resolve_ignore_mode(&project_dir, opts, &lock)?;
+        lock.ignore_mode = Some(ignore_mode);
+        save_install_lock(&lock, &claude_dir, dry_run)?;
+
         create_mcp_symlink(&project_dir, dry_run)?;
 
-        setup_claude_hooks(&claude_dir, dry_run)?;
+        setup_claude_hooks(&claude_dir, dry_run, &mut lock)?;
 
-        update_project_gitignore(&project_dir, dry_run)?;
-        install_git_hooks(&project_dir, dry_run)?;
+        ignore::install(&project_dir, ignore_mode, dry_run)?;
+        commands::git::warn_tracked(&project_dir);
+        install_git_hooks(&project_dir, dry_run, &opts.hooks)?;
 
         print_project_completion(dry_run)?;
     }
@@ -195,6 +356,21 @@ pub fn install_project(dry_run: bool, filter: &InstallFilter) -> Result<()> {
     Ok(())
 }
 
+/// Print the profiles in effect and how they were chosen
+fn print_profiles(profiles: &ActiveProfiles) {
+    let source = match profiles.selection {
+        Selection::Explicit => "selected",
+        Selection::Detected => "detected",
+    };
+    println!("{} {}", "Profiles:".cyan(), format!("({})", source).dimmed());
+    if profiles.active.is_empty() {
+        println!("  - {}", "none".dimmed());
+    }
+    for profile in &profiles.active {
+        println!("  - {} {}", profile.name, format!("- {}", profile.description).dimmed());
+    }
+}
+
 /// Ensure current directory is a git repository, initialize if not
 fn ensure_git_repository(dry_run: bool) -> Result<()> {
     if !is_git_repository() {
@@ -247,7 +423,9 @@ pub fn install_chat(dry_run: bool) -> Result<()> {
     let chat_dir = home_dir.join(".chat");
     ensure_directory(&chat_dir, dry_run)?;
 
-    templates::copy_chat_templates(&chat_dir, dry_run)?;
+    let mut lock = InstallLock::load(&chat_dir)?;
+    templates::copy_chat_templates(&chat_dir, dry_run, &mut lock)?;
+    save_install_lock(&lock, &chat_dir, dry_run)?;
 
     // Create .mcp.json symlink for Claude Code 2.1+ compatibility
     create_mcp_symlink(&chat_dir, dry_run)?;
@@ -275,17 +453,21 @@ const TEMPLATE_PREVIEW_LENGTH: usize = 200;
 /// Options for installing a JSON template
 struct JsonTemplateOptions<'a> {
     claude_dir: &'a Path,
+    templates: &'a TemplateSet,
     template_name: &'a str,
     target_name: &'a str,
     expand_env: bool,
     dry_run: bool,
+    merge_strategy: Option<MergeStrategy>,
 }
 
-/// Install JSON configuration from embedded template
-fn install_json_template(opts: JsonTemplateOptions) -> Result<()> {
+/// Install JSON configuration from a template of the set (embedded, a pack or the user overlay)
+///
+/// The merged result is recorded in `lock` under `target_name`, together with the keys
+/// and array entries the template contributed (undone by `hagi uninstall --global`).
+fn install_json_template(opts: JsonTemplateOptions, lock: &mut InstallLock) -> Result<()> {
     let target = opts.claude_dir.join(opts.target_name);
-    let template_str = templates::get_template(opts.template_name)
-        .with_context(|| format!("Failed to get embedded {} template", opts.template_name))?;
+    let template_str = opts.templates.get(opts.template_name)?;
 
     let mut template_content: serde_json::Value = serde_json::from_str(template_str)
         .with_context(|| {
@@ -315,52 +497,61 @@ fn install_json_template(opts: JsonTemplateOptions) -> Result<()> {
         println!("{} {}", action.yellow(), target.display());
 
         let env_note = if opts.expand_env { " (with environment variables expanded)" } else { "" };
+        let origin = opts.templates.origin(Path::new(opts.template_name)).map(|o| o.to_string()).unwrap_or_default();
         if opts.template_name == opts.target_name {
-            println!("  Template: embedded {}{}", opts.template_name, env_note);
+            println!("  Template: {} {}{}", origin, opts.template_name, env_note);
         } else {
-            println!("  Template: embedded {} → {}{}", opts.template_name, opts.target_name, env_note);
+            println!("  Template: {} {} → {}{}", origin, opts.template_name, opts.target_name, env_note);
         }
     } else {
-        utils::merge_json_file(&target, &template_content)
+        let (merged, contributions) = utils::merge_json_file(&target, &template_content, opts.merge_strategy)
             .with_context(|| format!("Failed to install {} to {}", opts.target_name, target.display()))?;
+        let written = serde_json::to_string_pretty(&merged)
+            .context("Failed to serialize merged JSON")?;
+        lock.record(
+            Path::new(opts.target_name),
+            Category::for_path(Path::new(opts.template_name)),
+            written.as_bytes(),
+        );
+        lock.record_contributions(Path::new(opts.target_name), contributions);
     }
 
     Ok(())
 }
 
-/// Install settings configuration from embedded template (rename settings.local.json → settings.json)
-fn install_settings(claude_dir: &Path, dry_run: bool) -> Result<()> {
-    install_json_template(JsonTemplateOptions {
-        claude_dir,
-        template_name: "settings.local.json",
-        target_name: "settings.json",
-        expand_env: false,
-        dry_run,
-    })
+/// Install settings configuration from the template set (rename settings.local.json → settings.json)
+fn install_settings(
+    claude_dir: &Path,
+    templates: &TemplateSet,
+    dry_run: bool,
+    merge_strategy: Option<MergeStrategy>,
+    lock: &mut InstallLock,
+) -> Result<()> {
+    install_json_template(
+        JsonTemplateOptions {
+            claude_dir,
+            templates,
+            template_name: templates::SETTINGS_TEMPLATE,
+            target_name: GLOBAL_SETTINGS,
+            expand_env: false,
+            dry_run,
+            merge_strategy,
+        },
+        lock,
+    )
 }
 
 // ============================================================================
 // Git & Project Configuration Helpers
 // ============================================================================
 
-/// Update .gitignore in project root
-fn update_project_gitignore(project_dir: &Path, dry_run: bool) -> Result<()> {
-    let entries = [
-        "/.claude/",
-        "/.mcp.json",
-        "/.serena/",
-    ];
-
-    if dry_run {
-        println!("{} {}", "Would update:".yellow(), project_dir.join(".gitignore").display());
-        for entry in &entries {
-            println!("  {} {}", "Would add:".yellow(), entry);
-        }
-    } else {
-        utils::update_gitignore(project_dir, &entries)?;
+/// Resolve the ignore mode: `--ignore-mode`, then the project config, then the last install
+fn resolve_ignore_mode(project_dir: &Path, opts: &ProjectOptions, lock: &InstallLock) -> Result<IgnoreMode> {
+    if let Some(mode) = opts.ignore_mode {
+        return Ok(mode);
     }
-
-    Ok(())
+    let config = ProjectConfig::load(project_dir)?;
+    Ok(config.ignore_mode.or(lock.ignore_mode).unwrap_or_default())
 }
 
 /// Check if current directory is a git repository
@@ -457,84 +648,49 @@ fn check_dependencies() -> Vec<String> {
     warnings
 }
 
-/// Make a file executable (Unix only)
-#[cfg(unix)]
-fn make_executable(path: &std::path::Path) -> Result<()> {
-    use std::os::unix::fs::PermissionsExt;
-    let mut perms = std::fs::metadata(path)?.permissions();
-    perms.set_mode(0o755);
-    std::fs::set_permissions(path, perms)?;
-    Ok(())
-}
-
-/// Write a git hook file and make it executable
-fn install_hook(hooks_dir: &std::path::Path, name: &str, content: &str) -> Result<()> {
-    let hook_path = hooks_dir.join(name);
-    std::fs::write(&hook_path, content)
-        .with_context(|| format!("Failed to write {} hook", name))?;
-
-    #[cfg(unix)]
-    make_executable(&hook_path)
-        .with_context(|| format!("Failed to make {} executable", name))?;
-
-    Ok(())
-}
-
-
-/// Make Claude Code hook script executable and print summary
-fn setup_claude_hooks(claude_dir: &Path, dry_run: bool) -> Result<()> {
+/// Remove the legacy jq guard script (replaced by `hagi guard`) and print summary
+///
+/// The script is only removed if hagi installed it and it is unmodified.
+fn setup_claude_hooks(claude_dir: &Path, dry_run: bool, lock: &mut InstallLock) -> Result<()> {
     println!("\n{}", "Claude Code hooks...".green());
 
-    let hook_script = claude_dir.join("hooks").join("check-claude-git.sh");
+    let legacy_path = Path::new(guard::LEGACY_SCRIPT);
+    let legacy_script = claude_dir.join(legacy_path);
+    let unmodified = lock.get(legacy_path).is_some_and(|entry| {
+        fs::read(&legacy_script).is_ok_and(|content| lock::content_hash(&content) == entry.hash)
+    });
 
     if dry_run {
+        if unmodified {
+            println!("{} {} (replaced by '{}')", "Would remove:".yellow(), legacy_script.display(), guard::GUARD_COMMAND);
+        }
         println!("{} PreToolUse hook for .claude/ git protection", "Would configure:".yellow());
     } else {
-        #[cfg(unix)]
-        if hook_script.exists() {
-            make_executable(&hook_script)
-                .with_context(|| "Failed to make Claude hook script executable")?;
+        if unmodified {
+            fs::remove_file(&legacy_script)
+                .with_context(|| format!("Failed to remove {}", legacy_script.display()))?;
+            let _ = fs::remove_dir(claude_dir.join("hooks"));
+            lock.files.remove(guard::LEGACY_SCRIPT);
+            lock.save(claude_dir)?;
+            println!("{} {} (replaced by '{}')", "Removed:".green(), legacy_script.display(), guard::GUARD_COMMAND);
         }
 
         println!("  ✅ {}", "Claude Code hooks configured".green());
-        println!("     {}", "- PreToolUse: Blocks .claude/ git operations".dimmed());
+        println!("     {}", format!("- PreToolUse: {} (blocks .claude/ git operations)", guard::GUARD_COMMAND).dimmed());
     }
 
     Ok(())
 }
 
-/// Install git hooks to .git/hooks/
-fn install_git_hooks(project_dir: &Path, dry_run: bool) -> Result<()> {
-    let git_hooks_dir = project_dir.join(".git").join("hooks");
-
-    if !git_hooks_dir.exists() {
+/// Install git hooks (chaining existing hooks, or registering with a hook manager)
+fn install_git_hooks(project_dir: &Path, dry_run: bool, opts: &githooks::HookOptions) -> Result<()> {
+    if !is_git_repository() {
         let msg = if dry_run { "would skip" } else { "skipping" };
-        println!("\n{} .git/hooks directory not found, {} git hooks installation", "⚠".yellow(), msg);
+        println!("\n{} Not a git repository, {} git hooks installation", "⚠".yellow(), msg);
         return Ok(());
     }
 
-    println!("\n{}", "Installing git hooks...".green());
-
-    let hooks = [
-        ("pre-commit", include_str!("../../templates/git-hooks/pre-commit")),
-        ("commit-msg", include_str!("../../templates/git-hooks/commit-msg")),
-    ];
-
-    if dry_run {
-        for (name, _) in &hooks {
-            println!("{} {}", "Would install:".yellow(), git_hooks_dir.join(name).display());
-        }
-    } else {
-        for (name, content) in &hooks {
-            install_hook(&git_hooks_dir, name, content)?;
-        }
-
-        println!("  ✅ {}", "Git hooks installed".green());
-        println!("     {}", "- pre-commit: Prevents direct commits to main/master".dimmed());
-        println!("     {}", "- commit-msg: Blocks Claude Code signatures".dimmed());
-    }
-
-    Ok(())
+    githooks::install(project_dir, dry_run, opts)
 }
 
 /// Print .claude sync notice for multi-machine workflow
@@ -582,6 +738,37 @@ fn print_claude_sync_notice() -> Result<()> {
     Ok(())
 }
 
+// ============================================================================
+// Guard Settings Migration
+// ============================================================================
+
+/// Point PreToolUse entries of installed settings at `hagi guard` (earlier versions ran the jq script)
+///
+/// Runs before the template is merged, so the merge sees the same command on both sides.
+fn migrate_guard_settings(settings_path: &Path, dry_run: bool) -> Result<()> {
+    if !settings_path.exists() {
+        return Ok(());
+    }
+    let mut settings = utils::read_json_file(settings_path)?;
+    if !guard::replace_legacy_script(&mut settings) {
+        return Ok(());
+    }
+
+    if dry_run {
+        println!(
+            "{} {} ({} → '{}')",
+            "Would update:".yellow(),
+            settings_path.display(),
+            guard::LEGACY_SCRIPT,
+            guard::GUARD_COMMAND
+        );
+        return Ok(());
+    }
+    utils::write_json_file(settings_path, &settings)?;
+    println!("  {} PreToolUse: {} → '{}'", "Migrated:".green(), guard::LEGACY_SCRIPT, guard::GUARD_COMMAND);
+    Ok(())
+}
+
 // ============================================================================
 // Commands to Skills Migration
 // ============================================================================
@@ -671,7 +858,7 @@ fn create_mcp_symlink(project_dir: &Path, dry_run: bool) -> Result<()> {
     }
 
     // Create relative symlink
-    symlink(".claude/mcp.json", &mcp_link)
+    symlink(MCP_SYMLINK_TARGET, &mcp_link)
         .with_context(|| format!("Failed to create symlink {}", mcp_link.display()))?;
 
     println!("  {} .mcp.json → .claude/mcp.json", "✅ Created:".green());
@@ -697,7 +884,7 @@ fn print_symlink_dry_run(mcp_link: &Path) -> Result<()> {
 #[cfg(unix)]
 fn is_correct_symlink(mcp_link: &Path) -> bool {
     std::fs::read_link(mcp_link)
-        .map(|target| target == Path::new(".claude/mcp.json"))
+        .map(|target| target == Path::new(MCP_SYMLINK_TARGET))
         .unwrap_or(false)
 }
 
@@ -1,4 +1,9 @@
+pub mod audit;
 pub mod config;
+pub mod diff;
+pub mod git;
+pub mod guard;
+pub mod hook;
 pub mod install;
 pub mod mcp;
 pub mod status;
@@ -2,9 +2,17 @@ use anyhow::{Context, Result};
 use colored::*;
 use std::collections::{HashMap, HashSet};
 use std::env;
-
+use std::path::Path;
+
+use crate::commands;
+use crate::githooks;
+use crate::ignore::{self, IgnoreMode};
+use crate::lock::{self, InstallLock};
+use crate::profiles::{ActiveProfiles, Selection};
+use crate::project_config::ProjectConfig;
 use crate::templates;
 use crate::utils;
+use crate::worktree;
 
 /// Show installation status
 pub fn status() -> Result<()> {
@@ -53,6 +61,21 @@ fn check_global_configuration() -> Result<()> {
         println!("{} {} - {}", "✗".red(), "~/.claude/settings.json".bold(), "not found".dimmed());
     }
 
+    // Files from `hagi install --global` (CLAUDE.md, instructions, skills, agents)
+    let install_lock = InstallLock::load(&claude_dir)?;
+    if install_lock.get(Path::new(templates::CLAUDE_MD)).is_some() {
+        println!("{} {} - {}", "✓".green(), "~/.claude/CLAUDE.md".bold(), "installed".dimmed());
+    }
+    let counts: Vec<String> = install_lock
+        .count_by_category()
+        .iter()
+        .filter(|(name, _)| !matches!(**name, "config" | "docs"))
+        .map(|(name, count)| format!("{}: {}", name, count))
+        .collect();
+    if !counts.is_empty() {
+        println!("{} {} ({})", "✓".green(), "~/.claude/ templates".bold(), counts.join(", "));
+    }
+
     Ok(())
 }
 
@@ -66,10 +89,19 @@ fn check_project_configuration() -> Result<()> {
     if !claude_dir.exists() {
         println!("{} {} - {}", "✗".red(), ".claude/".bold(), "not found".dimmed());
         println!("\nRun {} to install project configuration", "hagi install".yellow());
+        worktree::print_shared_hint(&project_dir);
         return Ok(());
     }
 
-    println!("{} {} - {}", "✓".green(), ".claude/".bold(), "installed".dimmed());
+    match worktree::shared_target(&project_dir) {
+        Some(target) => println!(
+            "{} {} - {}",
+            "✓".green(),
+            ".claude/".bold(),
+            format!("shared ({})", target.display()).dimmed()
+        ),
+        None => println!("{} {} - {}", "✓".green(), ".claude/".bold(), "installed".dimmed()),
+    }
 
     // Check key template files
     let files = [
@@ -108,6 +140,102 @@ fn check_project_configuration() -> Result<()> {
         println!("  {} .claude/skills/ - {}", "✗".red(), "not found".dimmed());
     }
 
+    check_install_lock(&claude_dir)?;
+    check_profiles(&project_dir, &claude_dir)?;
+    check_ignore_entries(&project_dir, &claude_dir)?;
+    commands::git::warn_tracked(&project_dir);
+    if githooks::detect_mode(&project_dir).is_ok() {
+        githooks::print_status(&project_dir)?;
+    }
+
+    Ok(())
+}
+
+/// Show the project profiles a reinstall would use
+fn check_profiles(project_dir: &Path, claude_dir: &Path) -> Result<()> {
+    let install_lock = InstallLock::load(claude_dir)?;
+    let profiles = ActiveProfiles::resolve(project_dir, &install_lock.profiles)?;
+    let source = match profiles.selection {
+        Selection::Explicit => "selected with --profile",
+        Selection::Detected => "detected",
+    };
+    let names = profiles.names();
+    let names = if names.is_empty() { "none".to_string() } else { names.join(", ") };
+    println!("  {} profiles: {} {}", "ℹ".blue(), names, format!("({})", source).dimmed());
+    Ok(())
+}
+
+/// Show the ignore mode and which file holds hagi's entries
+fn check_ignore_entries(project_dir: &Path, claude_dir: &Path) -> Result<()> {
+    let install_lock = InstallLock::load(claude_dir)?;
+    let config = ProjectConfig::load(project_dir)?;
+    let mode = config.ignore_mode.or(install_lock.ignore_mode).unwrap_or_default();
+    println!("  {} ignore mode: {}", "ℹ".blue(), mode);
+
+    for other in IgnoreMode::ALL {
+        let Ok(path) = other.path(project_dir) else {
+            continue;
+        };
+        let display = ignore::display_path(project_dir, &path);
+        let entries = ignore::present_entries(&path);
+        if other == mode {
+            let missing: Vec<&str> = ignore::IGNORE_ENTRIES
+                .iter()
+                .filter(|e| !entries.contains(e))
+                .copied()
+                .collect();
+            if missing.is_empty() {
+                println!("    {} {}", "✓".green(), display);
+            } else {
+                println!("    {} {} - {}", "✗".red(), display, format!("missing {}", missing.join(", ")).dimmed());
+            }
+        } else if !entries.is_empty() {
+            println!(
+                "    {} {} - {}",
+                "⚠".yellow(),
+                display,
+                format!("also has {} (moved on the next hagi install)", entries.join(", ")).dimmed()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Show files recorded in the install lock
+fn check_install_lock(claude_dir: &Path) -> Result<()> {
+    if !claude_dir.join(lock::LOCK_FILE).exists() {
+        println!(
+            "  {} .claude/{} - {}",
+            "○".dimmed(),
+            lock::LOCK_FILE,
+            "not found (run hagi install to create)".dimmed()
+        );
+        return Ok(());
+    }
+
+    let install_lock = InstallLock::load(claude_dir)?;
+    let counts: Vec<String> = install_lock
+        .count_by_category()
+        .iter()
+        .map(|(name, count)| format!("{}: {}", name, count))
+        .collect();
+    println!(
+        "  {} .claude/{} ({} files: {})",
+        "✓".green(),
+        lock::LOCK_FILE,
+        install_lock.files.len(),
+        counts.join(", ")
+    );
+
+    let missing: Vec<&String> = install_lock
+        .files
+        .keys()
+        .filter(|path| !claude_dir.join(path).exists())
+        .collect();
+    for path in missing {
+        println!("    {} .claude/{} - {}", "✗".red(), path, "recorded but missing".dimmed());
+    }
+
     Ok(())
 }
 
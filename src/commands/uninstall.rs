@@ -2,57 +2,141 @@ use anyhow::{Context, Result};
 use colored::*;
 use std::env;
 use std::fs;
-use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
+use crate::commands::install;
+use crate::githooks::{self, HookMode, HookState};
+use crate::ignore::{self, IgnoreMode};
+use crate::json_merge;
+use crate::keep;
+use crate::lock::{self, InstallLock};
+use crate::packs::FetchPolicy;
+use crate::templates::{self, Category};
 use crate::utils;
+use crate::worktree;
+
+// ============================================================================
+// Global Uninstall
+// ============================================================================
+
+/// `settings.json` changes an uninstall will make
+struct SettingsRevert {
+    /// The file with hagi's keys taken out
+    restored: serde_json::Value,
+    changes: Vec<json_merge::KeyChange>,
+    /// Only hagi's keys were left: remove the file
+    remove_file: bool,
+}
 
 /// Uninstall global configuration from ~/.claude/
-pub fn uninstall_global(skip_confirm: bool) -> Result<()> {
+///
+/// Like a project uninstall, only unmodified files recorded in the install lock are
+/// removed. `settings.json` existed before hagi for most users, so only the keys and
+/// array entries recorded at install time are taken out, and values hagi overwrote
+/// are restored; the file is removed only when nothing else is left in it.
+pub fn uninstall_global(opts: &UninstallOptions) -> Result<()> {
+    if opts.dry_run {
+        println!("{}", "[DRY RUN MODE]".yellow().bold());
+    }
     println!("{}", "Uninstalling global configuration...".yellow());
 
     let claude_dir = match utils::claude_dir() {
-        Ok(dir) => dir,
-        Err(_) => {
+        Ok(dir) if dir.exists() => dir,
+        _ => {
             println!("{} ~/.claude/ not found", "✗".red());
             return Ok(());
         }
     };
 
-    // Check what will be removed
-    let mcp_json = claude_dir.join("mcp.json");
-    let settings_json = claude_dir.join("settings.json");
+    let mut install_lock = InstallLock::load(&claude_dir)?;
+    let settings_path = Path::new(install::GLOBAL_SETTINGS);
+    let settings_json = claude_dir.join(install::GLOBAL_SETTINGS);
+    let settings_name = format!("~/.claude/{}", install::GLOBAL_SETTINGS);
 
-    let mut files_to_remove = Vec::new();
-    if mcp_json.exists() {
-        files_to_remove.push(("~/.claude/mcp.json", mcp_json.clone()));
+    if claude_dir.join("mcp.json").exists() {
+        println!(
+            "{} ~/.claude/mcp.json is not managed by hagi's global install; left in place",
+            "○".dimmed()
+        );
     }
-    if settings_json.exists() {
-        files_to_remove.push(("~/.claude/settings.json", settings_json.clone()));
+
+    let revert = plan_settings_revert(&settings_json, &settings_name, &install_lock, opts)?;
+    let mut plan = UninstallPlan::default();
+    plan_lock_files(&claude_dir, &install_lock, opts, &mut plan)?;
+    plan.remove_files.retain(|path| path != install::GLOBAL_SETTINGS);
+    plan.modified.retain(|path| path != install::GLOBAL_SETTINGS);
+    // CLAUDE.md is recorded with the personal section in it: keep it once that was filled in
+    if let Some(i) = plan.remove_files.iter().position(|path| path == templates::CLAUDE_MD)
+        && has_personal_section(&claude_dir.join(templates::CLAUDE_MD), &install_lock)?
+    {
+        let path = plan.remove_files.remove(i);
+        plan.modified.push(path);
     }
 
-    if files_to_remove.is_empty() {
-        println!("{} No global configuration found", "○".dimmed());
+    if revert.is_none() && plan.remove_files.is_empty() {
+        if plan.modified.is_empty() && plan.kept.is_empty() {
+            println!("{} No global configuration found", "○".dimmed());
+        } else {
+            print_global_plan(&plan, None, &settings_name, opts);
+            println!("\n{} Nothing to uninstall", "○".dimmed());
+        }
         return Ok(());
     }
+    print_global_plan(&plan, revert.as_ref(), &settings_name, opts);
 
-    // Show what will be removed
-    println!("\n{}", "The following files will be removed:".yellow());
-    for (display_name, _) in &files_to_remove {
-        println!("  - {}", display_name);
+    if opts.dry_run {
+        println!("{}", "\nDry run completed. No files were modified.".yellow());
+        return Ok(());
     }
-
-    // Confirmation prompt
-    if !skip_confirm && !confirm("\nProceed with uninstallation?")? {
+    if !opts.yes && !utils::confirm("\nProceed with uninstallation?")? {
         println!("{}", "Aborted.".dimmed());
         return Ok(());
     }
 
-    // Remove files
-    for (display_name, path) in files_to_remove {
-        match fs::remove_file(&path) {
-            Ok(_) => println!("{} Removed {}", "✓".green(), display_name),
-            Err(e) => println!("{} Failed to remove {}: {}", "✗".red(), display_name, e),
+    if let Some(revert) = revert {
+        if revert.remove_file {
+            fs::remove_file(&settings_json).with_context(|| format!("Failed to remove {}", settings_name))?;
+            println!("{} Removed {}", "✓".green(), settings_name);
+        } else {
+            utils::backup_file(&settings_json)?;
+            utils::cleanup_old_backups(&settings_json, utils::DEFAULT_MAX_BACKUPS)?;
+            utils::write_json_file(&settings_json, &revert.restored)?;
+            println!("{} Reverted hagi's keys in {} (your own settings kept)", "✓".green(), settings_name);
+        }
+        install_lock.remove(settings_path);
+    }
+
+    for path in &plan.remove_files {
+        let file = claude_dir.join(path);
+        fs::remove_file(&file).with_context(|| format!("Failed to remove {}", file.display()))?;
+        println!("{} Removed ~/.claude/{}", "✓".green(), path);
+        install_lock.files.remove(path);
+
+        let base = claude_dir.join(lock::BASE_DIR).join(path);
+        for leftover in [base, templates::new_file_path(&file)] {
+            if leftover.exists() {
+                fs::remove_file(&leftover).with_context(|| format!("Failed to remove {}", leftover.display()))?;
+            }
+        }
+        // Only the directories hagi's files were in; ~/.claude/ also holds Claude Code's own data
+        for dir in Path::new(path).ancestors().skip(1).filter(|d| !d.as_os_str().is_empty()) {
+            if fs::remove_dir(claude_dir.join(dir)).is_err() {
+                break;
+            }
+        }
+    }
+
+    let lock_path = claude_dir.join(lock::LOCK_FILE);
+    if install_lock.files.is_empty() {
+        if lock_path.exists() {
+            fs::remove_file(&lock_path).context("Failed to remove install lock")?;
         }
+        let base_dir = claude_dir.join(lock::BASE_DIR);
+        if base_dir.exists() {
+            fs::remove_dir_all(&base_dir).context("Failed to remove merge bases")?;
+        }
+    } else {
+        install_lock.save(&claude_dir)?;
     }
 
     // Try to remove ~/.claude/ if empty
@@ -70,89 +154,432 @@ pub fn uninstall_global(skip_confirm: bool) -> Result<()> {
     Ok(())
 }
 
-/// Uninstall project-specific configuration from .claude/
-pub fn uninstall_project(skip_confirm: bool) -> Result<()> {
+/// Work out which of hagi's keys can be taken out of `settings.json`
+fn plan_settings_revert(
+    settings_json: &Path,
+    settings_name: &str,
+    install_lock: &InstallLock,
+    opts: &UninstallOptions,
+) -> Result<Option<SettingsRevert>> {
+    let settings_path = Path::new(install::GLOBAL_SETTINGS);
+    if !settings_json.exists() || install_lock.get(settings_path).is_none() || opts.keep.contains(&Category::Config) {
+        return Ok(None);
+    }
+    let Some(contributions) = install_lock.contributions(settings_path) else {
+        println!(
+            "{} {} was installed by an older hagi that did not record which keys it merged; left in place",
+            "⚠".yellow(),
+            settings_name
+        );
+        println!("  Remove hagi's permissions and hooks from it by hand if you no longer need them");
+        return Ok(None);
+    };
+
+    let current = utils::read_json_file(settings_json)?;
+    let (restored, changes) = json_merge::unmerge(&current, contributions);
+    let remove_file = restored.as_object().is_some_and(|obj| obj.is_empty());
+    Ok(Some(SettingsRevert { restored, changes, remove_file }))
+}
+
+/// Whether the hagi:keep blocks of `~/.claude/CLAUDE.md` differ from the template's
+///
+/// The template comes from the set the global install used (its packs pinned in `lock`).
+fn has_personal_section(claude_md: &Path, lock: &InstallLock) -> Result<bool> {
+    let existing = fs::read_to_string(claude_md).with_context(|| format!("Failed to read {}", claude_md.display()))?;
+    let (_, template_set) = install::global_template_set(lock, FetchPolicy::Pinned)?;
+    let template = template_set.get(templates::CLAUDE_MD)?;
+    Ok(keep::has_blocks(&existing) && keep::apply(template, &existing).text != template)
+}
+
+fn print_global_plan(plan: &UninstallPlan, revert: Option<&SettingsRevert>, settings_name: &str, opts: &UninstallOptions) {
+    let verb = if opts.dry_run { "Would remove:" } else { "Will remove:" };
+
+    println!();
+    for path in &plan.remove_files {
+        println!("{} ~/.claude/{}", verb.red(), path);
+    }
+    if let Some(revert) = revert {
+        if revert.remove_file {
+            println!("{} {} (only hagi's keys are left in it)", verb.red(), settings_name);
+        } else {
+            let verb = if opts.dry_run { "Would revert:" } else { "Will revert:" };
+            println!("{} {} (hagi's keys)", verb.red(), settings_name);
+            json_merge::print_changes(&revert.changes);
+        }
+    }
+    for path in &plan.modified {
+        println!("{} ~/.claude/{} (modified since install)", "Keep:".blue(), path);
+    }
+    for path in &plan.kept {
+        println!("{} ~/.claude/{} (--keep)", "Keep:".blue(), path);
+    }
+}
+
+// ============================================================================
+// Project Uninstall
+// ============================================================================
+
+/// Project uninstall settings given on the command line
+#[derive(Debug, Default)]
+pub struct UninstallOptions {
+    /// Skip the confirmation prompt
+    pub yes: bool,
+    /// Show what would be removed without removing anything
+    pub dry_run: bool,
+    /// Categories whose files are left in place
+    pub keep: Vec<Category>,
+    /// Remove the whole .claude/ directory and hagi artifacts even if modified
+    pub purge: bool,
+}
+
+/// What a project uninstall will remove and keep
+#[derive(Default)]
+struct UninstallPlan {
+    /// Unmodified hagi-owned files (relative to .claude/)
+    remove_files: Vec<String>,
+    /// Files modified since install (kept)
+    modified: Vec<String>,
+    /// Files kept because of `--keep`
+    kept: Vec<String>,
+    /// `.mcp.json` is hagi's symlink
+    remove_symlink: bool,
+    /// `.mcp.json` exists but is not hagi's symlink
+    foreign_symlink: bool,
+    /// How git hooks are installed
+    hook_mode: Option<HookMode>,
+    /// Git hooks identical to what hagi installed
+    remove_hooks: Vec<HookState>,
+    /// Git hooks that were changed since install
+    modified_hooks: Vec<HookState>,
+    /// Ignore entries hagi added, per file (.gitignore and/or info/exclude)
+    ignore_entries: Vec<(PathBuf, Vec<&'static str>)>,
+}
+
+/// Uninstall project-specific configuration
+///
+/// Only files recorded in the install lock and unmodified since install are removed,
+/// so designs, notes and synced files stay. `--purge` removes .claude/ entirely.
+pub fn uninstall_project(opts: &UninstallOptions) -> Result<()> {
+    if opts.dry_run {
+        println!("{}", "[DRY RUN MODE]".yellow().bold());
+    }
     println!("{}", "Uninstalling project configuration...".yellow());
 
     let project_dir = env::current_dir().context("Failed to get current directory")?;
     let claude_dir = project_dir.join(".claude");
 
-    if !claude_dir.exists() {
-        println!("{} .claude/ not found", "✗".red());
+    if let Some(target) = worktree::shared_target(&project_dir) {
+        return uninstall_shared_link(&project_dir, &target, opts);
+    }
+
+    let install_lock = InstallLock::load(&claude_dir)?;
+    if claude_dir.exists() && install_lock.files.is_empty() && !opts.purge {
+        println!(
+            "{} No install lock found (.claude/{}); cannot tell which files hagi wrote.",
+            "⚠".yellow(),
+            lock::LOCK_FILE
+        );
+        println!("  Run {} to remove .claude/ entirely", "hagi uninstall --purge".yellow());
+    }
+
+    let plan = build_plan(&project_dir, &claude_dir, &install_lock, opts)?;
+    print_plan(&project_dir, &plan, opts, claude_dir.exists());
+
+    if plan.is_empty() && !(opts.purge && claude_dir.exists()) {
+        println!("\n{} Nothing to uninstall", "○".dimmed());
         return Ok(());
     }
 
-    // Show what will be removed
-    println!("\n{}", "The following will be removed:".yellow());
-    println!("  - .claude/ directory and all its contents");
-    println!("  - hagi-related patterns from .gitignore");
+    if opts.dry_run {
+        println!("{}", "\nDry run completed. No files were modified.".yellow());
+        return Ok(());
+    }
 
-    // Confirmation prompt
-    if !skip_confirm && !confirm("\nProceed with uninstallation?")? {
+    if !opts.yes && !utils::confirm("\nProceed with uninstallation?")? {
         println!("{}", "Aborted.".dimmed());
         return Ok(());
     }
 
-    // Remove .claude/ directory
-    match fs::remove_dir_all(&claude_dir) {
-        Ok(_) => println!("{} Removed .claude/", "✓".green()),
-        Err(e) => {
-            println!("{} Failed to remove .claude/: {}", "✗".red(), e);
-            return Err(e.into());
+    if opts.purge {
+        if claude_dir.exists() {
+            fs::remove_dir_all(&claude_dir).context("Failed to remove .claude/")?;
+            println!("{} Removed .claude/", "✓".green());
         }
+    } else {
+        remove_claude_files(&claude_dir, install_lock, &plan.remove_files)?;
     }
 
-    // Clean up .gitignore
-    cleanup_gitignore(&project_dir)?;
+    if plan.remove_symlink {
+        fs::remove_file(project_dir.join(".mcp.json")).context("Failed to remove .mcp.json")?;
+        println!("{} Removed .mcp.json symlink", "✓".green());
+    }
+
+    if let Some(mode) = &plan.hook_mode {
+        for hook in &plan.remove_hooks {
+            githooks::remove_hook(&project_dir, mode, hook)?;
+            println!("{} Removed {} hook ({})", "✓".green(), hook.name, mode);
+        }
+    }
+
+    for (path, _) in &plan.ignore_entries {
+        ignore::uninstall(path)?;
+        println!("{} Cleaned up {}", "✓".green(), ignore::display_path(&project_dir, path));
+    }
 
     println!("{}", "\n✅ Project configuration uninstalled successfully!".green().bold());
     Ok(())
 }
 
-/// Remove hagi-related patterns from .gitignore
-fn cleanup_gitignore(project_dir: &std::path::Path) -> Result<()> {
-    let gitignore_path = project_dir.join(".gitignore");
+/// Unlink a worktree sharing another worktree's .claude/ (`hagi install --shared`)
+///
+/// The shared files, git hooks and ignore entries belong to the main worktree and stay.
+fn uninstall_shared_link(project_dir: &Path, target: &Path, opts: &UninstallOptions) -> Result<()> {
+    let mcp_link = project_dir.join(".mcp.json");
+    let remove_mcp_link = fs::read_link(&mcp_link).is_ok_and(|t| t == Path::new(install::MCP_SYMLINK_TARGET));
+    let verb = if opts.dry_run { "Would remove:" } else { "Will remove:" };
+
+    println!();
+    println!("{} .claude (symlink to {}; the shared files stay)", verb.red(), target.display());
+    if remove_mcp_link {
+        println!("{} .mcp.json (symlink)", verb.red());
+    }
+    println!(
+        "  {}",
+        "Git hooks and ignore entries are shared with the main worktree; uninstall there to remove them".dimmed()
+    );
 
-    if !gitignore_path.exists() {
+    if opts.dry_run {
+        println!("{}", "\nDry run completed. No files were modified.".yellow());
+        return Ok(());
+    }
+    if !opts.yes && !utils::confirm("\nProceed with uninstallation?")? {
+        println!("{}", "Aborted.".dimmed());
         return Ok(());
     }
 
-    let content = fs::read_to_string(&gitignore_path)
-        .context("Failed to read .gitignore")?;
+    fs::remove_file(project_dir.join(".claude")).context("Failed to remove .claude symlink")?;
+    println!("{} Removed .claude symlink", "✓".green());
+    if remove_mcp_link {
+        fs::remove_file(&mcp_link).context("Failed to remove .mcp.json")?;
+        println!("{} Removed .mcp.json symlink", "✓".green());
+    }
+    println!("{}", "\n✅ Project configuration uninstalled successfully!".green().bold());
+    Ok(())
+}
 
-    let hagi_patterns = [
-        "/.claude/",
-        "/.serena/",
-    ];
+impl UninstallPlan {
+    fn is_empty(&self) -> bool {
+        self.remove_files.is_empty()
+            && !self.remove_symlink
+            && self.remove_hooks.is_empty()
+            && self.ignore_entries.is_empty()
+    }
+}
 
-    let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-    let original_len = lines.len();
+fn build_plan(
+    project_dir: &Path,
+    claude_dir: &Path,
+    install_lock: &InstallLock,
+    opts: &UninstallOptions,
+) -> Result<UninstallPlan> {
+    let mut plan = UninstallPlan::default();
+    plan_lock_files(claude_dir, install_lock, opts, &mut plan)?;
 
-    // Remove hagi-related patterns
-    lines.retain(|line| {
-        let trimmed = line.trim();
-        !hagi_patterns.contains(&trimmed)
-    });
+    let mcp_link = project_dir.join(".mcp.json");
+    if mcp_link.is_symlink() || mcp_link.exists() {
+        let is_hagi_link = fs::read_link(&mcp_link)
+            .is_ok_and(|target| target == Path::new(install::MCP_SYMLINK_TARGET));
+        if is_hagi_link || (opts.purge && mcp_link.is_symlink()) {
+            plan.remove_symlink = true;
+        } else {
+            plan.foreign_symlink = true;
+        }
+    }
 
-    if lines.len() < original_len {
-        // Write back cleaned .gitignore
-        let new_content = lines.join("\n");
-        fs::write(&gitignore_path, new_content)
-            .context("Failed to write .gitignore")?;
-        println!("{} Cleaned up .gitignore", "✓".green());
+    if let Ok(mode) = githooks::detect_mode(project_dir) {
+        for hook in githooks::hook_states(project_dir, &mode)? {
+            if !hook.installed {
+                continue;
+            }
+            if hook.modified && !opts.purge {
+                plan.modified_hooks.push(hook);
+            } else {
+                plan.remove_hooks.push(hook);
+            }
+        }
+        plan.hook_mode = Some(mode);
     }
 
+    // Look in both files: the mode may have changed since the entries were written
+    for mode in IgnoreMode::ALL {
+        let Ok(path) = mode.path(project_dir) else {
+            continue;
+        };
+        let entries = ignore::present_entries(&path);
+        if !entries.is_empty() {
+            plan.ignore_entries.push((path, entries));
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Sort the files recorded in the lock into removed, modified and `--keep`
+fn plan_lock_files(
+    claude_dir: &Path,
+    install_lock: &InstallLock,
+    opts: &UninstallOptions,
+    plan: &mut UninstallPlan,
+) -> Result<()> {
+    for (path, entry) in &install_lock.files {
+        let file = claude_dir.join(path);
+        if !file.is_file() {
+            continue;
+        }
+        if entry.category.is_some_and(|c| opts.keep.contains(&c)) {
+            plan.kept.push(path.clone());
+            continue;
+        }
+        let content = fs::read(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+        if lock::content_hash(&content) == entry.hash || opts.purge {
+            plan.remove_files.push(path.clone());
+        } else {
+            plan.modified.push(path.clone());
+        }
+    }
     Ok(())
 }
 
-/// Show confirmation prompt and get user response
-fn confirm(message: &str) -> Result<bool> {
-    print!("{} [Y/n]: ", message);
-    io::stdout().flush()?;
+fn print_plan(project_dir: &Path, plan: &UninstallPlan, opts: &UninstallOptions, claude_exists: bool) {
+    let verb = if opts.dry_run { "Would remove:" } else { "Will remove:" };
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
+    println!();
+    if opts.purge && claude_exists {
+        println!("{} .claude/ (entire directory, including files hagi did not write)", verb.red());
+    } else {
+        for path in &plan.remove_files {
+            println!("{} .claude/{}", verb.red(), path);
+        }
+    }
+    if plan.remove_symlink {
+        println!("{} .mcp.json (symlink)", verb.red());
+    }
+    for hook in &plan.remove_hooks {
+        let restore = if hook.chained { " (restoring the chained hook)" } else { "" };
+        println!("{} {} hook in {}{}", verb.red(), hook.name, hook.path.display(), restore);
+    }
+    for (path, entries) in &plan.ignore_entries {
+        let display = ignore::display_path(project_dir, path);
+        for entry in entries {
+            println!("{} {} from {}", verb.red(), entry, display);
+        }
+    }
 
-    let input = input.trim().to_lowercase();
-    Ok(input.is_empty() || input == "y" || input == "yes")
+    if opts.purge {
+        return;
+    }
+    for path in &plan.modified {
+        println!("{} .claude/{} (modified since install)", "Keep:".blue(), path);
+    }
+    for path in &plan.kept {
+        println!("{} .claude/{} (--keep)", "Keep:".blue(), path);
+    }
+    if plan.foreign_symlink {
+        println!("{} .mcp.json (not the symlink hagi created)", "Keep:".blue());
+    }
+    for hook in &plan.modified_hooks {
+        println!("{} {} hook in {} (modified since install)", "Keep:".blue(), hook.name, hook.path.display());
+    }
+    if !plan.modified.is_empty() || !plan.modified_hooks.is_empty() {
+        println!("  {}", "Use --purge to remove modified files as well".dimmed());
+    }
+}
+
+/// Remove files from .claude/, then their merge bases, empty directories and lock entries
+fn remove_claude_files(claude_dir: &Path, mut install_lock: InstallLock, files: &[String]) -> Result<()> {
+    for path in files {
+        let file = claude_dir.join(path);
+        fs::remove_file(&file).with_context(|| format!("Failed to remove {}", file.display()))?;
+        println!("{} Removed .claude/{}", "✓".green(), path);
+
+        let base = claude_dir.join(lock::BASE_DIR).join(path);
+        for leftover in [base, templates::new_file_path(&file)] {
+            if leftover.exists() {
+                fs::remove_file(&leftover).with_context(|| format!("Failed to remove {}", leftover.display()))?;
+            }
+        }
+        install_lock.files.remove(path);
+    }
+
+    let lock_path = claude_dir.join(lock::LOCK_FILE);
+    if install_lock.files.is_empty() {
+        if lock_path.exists() {
+            fs::remove_file(&lock_path).context("Failed to remove install lock")?;
+        }
+        let base_dir = claude_dir.join(lock::BASE_DIR);
+        if base_dir.exists() {
+            fs::remove_dir_all(&base_dir).context("Failed to remove merge bases")?;
+        }
+    } else if lock_path.exists() {
+        install_lock.save(claude_dir)?;
+    }
+
+    remove_empty_dirs(claude_dir)?;
+    if claude_dir.exists() && fs::read_dir(claude_dir)?.next().is_none() {
+        fs::remove_dir(claude_dir).context("Failed to remove .claude/")?;
+        println!("{} Removed empty .claude/", "✓".green());
+    } else if claude_dir.exists() {
+        println!("{} .claude/ kept (contains files hagi did not write)", "○".dimmed());
+    }
+    Ok(())
+}
+
+/// Remove empty subdirectories below `dir` (bottom-up); `dir` itself is left alone
+fn remove_empty_dirs(dir: &Path) -> Result<()> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() && !path.is_symlink() {
+            remove_empty_dirs(&path)?;
+            if fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_files_survive_uninstall() {
+        let tmp = tempfile::tempdir().unwrap();
+        let claude_dir = tmp.path().join(".claude");
+        let instructions = claude_dir.join("instructions");
+        fs::create_dir_all(&instructions).unwrap();
+
+        let mut install_lock = InstallLock::load(&claude_dir).unwrap();
+        for (name, content) in [("hagi.md", "from hagi"), ("edited.md", "from hagi")] {
+            fs::write(instructions.join(name), content).unwrap();
+            install_lock.record(&Path::new("instructions").join(name), Some(Category::Instructions), content.as_bytes());
+        }
+        install_lock.save(&claude_dir).unwrap();
+        fs::write(instructions.join("edited.md"), "edited").unwrap();
+        fs::write(instructions.join("mine.md"), "written by the user").unwrap();
+
+        let mut plan = UninstallPlan::default();
+        plan_lock_files(&claude_dir, &install_lock, &UninstallOptions::default(), &mut plan).unwrap();
+        assert_eq!(plan.remove_files, vec!["instructions/hagi.md"]);
+        assert_eq!(plan.modified, vec!["instructions/edited.md"]);
+
+        remove_claude_files(&claude_dir, install_lock, &plan.remove_files).unwrap();
+        assert!(!instructions.join("hagi.md").exists());
+        assert_eq!(fs::read_to_string(instructions.join("mine.md")).unwrap(), "written by the user");
+        assert!(instructions.join("edited.md").exists());
+    }
 }
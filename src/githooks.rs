@@ -0,0 +1,679 @@
+use anyhow::{Context, Result};
+use colored::*;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::project_config::{MessageMode, ProjectConfig};
+use crate::utils;
+
+/// Git hooks hagi installs (name, shim calling `hagi hook run <name>`)
+const GIT_HOOKS: &[(&str, &str)] = &[
+    ("pre-commit", include_str!("../templates/git-hooks/pre-commit")),
+    ("commit-msg", include_str!("../templates/git-hooks/commit-msg")),
+    ("pre-push", include_str!("../templates/git-hooks/pre-push")),
+    ("prepare-commit-msg", include_str!("../templates/git-hooks/prepare-commit-msg")),
+];
+
+/// Hooks installed only when enabled (`--pre-push` / `--message-mode strip`, or .hagi.toml)
+const OPTIONAL_HOOKS: &[&str] = &["pre-push", "prepare-commit-msg"];
+
+/// Lines identifying hooks written by hagi before dispatchers existed (full shell hooks)
+const LEGACY_MARKERS: &[&str] = &[
+    "# Layer 2: Block .claude/ files from being committed",
+    "# Check for Claude Code signature in commit message",
+];
+
+/// Suffix for an existing hook moved aside by hagi (run first by the dispatcher)
+const LOCAL_SUFFIX: &str = ".local";
+
+/// First line after the shebang of every hook hagi writes to a hooks directory
+const DISPATCHER_MARKER: &str = "# hagi-managed hook";
+
+/// Block markers for hagi's lines in husky/lefthook files
+const BLOCK_START: &str = "# >>> hagi >>>";
+const BLOCK_END: &str = "# <<< hagi <<<";
+
+/// Lefthook config files (any of them means the project uses lefthook)
+const LEFTHOOK_CONFIGS: &[&str] = &["lefthook.yml", ".lefthook.yml", "lefthook.yaml", ".lefthook.yaml"];
+
+/// Local lefthook overrides (merged by lefthook, not meant to be committed)
+const LEFTHOOK_LOCAL: &str = "lefthook-local.yml";
+
+/// pre-commit framework config file
+const PRE_COMMIT_CONFIG: &str = ".pre-commit-config.yaml";
+
+/// Hook scripts for hook managers, relative to the git common dir
+const SCRIPTS_DIR: &str = "hagi/hooks";
+
+// ============================================================================
+// Mode Detection
+// ============================================================================
+
+/// How hagi's git hooks are wired into the repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookMode {
+    /// Dispatchers in the git hooks directory (`custom` = set by core.hooksPath)
+    HooksDir { dir: PathBuf, custom: bool },
+    /// Registered in `.husky/<hook>`
+    Husky,
+    /// Registered in `lefthook-local.yml`
+    Lefthook,
+    /// pre-commit framework: hagi prints a local hook entry to add to the config
+    PreCommit,
+}
+
+impl fmt::Display for HookMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookMode::HooksDir { custom: false, .. } => write!(f, "hooks directory"),
+            HookMode::HooksDir { custom: true, dir } => {
+                write!(f, "core.hooksPath ({})", dir.display())
+            }
+            HookMode::Husky => write!(f, "husky"),
+            HookMode::Lefthook => write!(f, "lefthook"),
+            HookMode::PreCommit => write!(f, "pre-commit framework"),
+        }
+    }
+}
+
+/// Detect how hooks should be installed
+///
+/// Hook managers win over a plain hooks directory: husky (`.husky/` or a
+/// core.hooksPath inside it), then lefthook, then the pre-commit framework.
+pub fn detect_mode(project_dir: &Path) -> Result<HookMode> {
+    let hooks_path = utils::git_output(project_dir, &["config", "--get", "core.hooksPath"]);
+
+    if project_dir.join(".husky").is_dir() || hooks_path.as_deref().is_some_and(|p| p.contains(".husky")) {
+        return Ok(HookMode::Husky);
+    }
+    if LEFTHOOK_CONFIGS.iter().any(|f| project_dir.join(f).exists()) {
+        return Ok(HookMode::Lefthook);
+    }
+    if project_dir.join(PRE_COMMIT_CONFIG).exists() {
+        return Ok(HookMode::PreCommit);
+    }
+
+    let dir = git_path(project_dir, "hooks")?;
+    Ok(HookMode::HooksDir { dir, custom: hooks_path.is_some() })
+}
+
+// ============================================================================
+// Install
+// ============================================================================
+
+/// Optional git hooks requested on the command line
+#[derive(Debug, Default, Clone)]
+pub struct HookOptions {
+    /// Install the pre-push hook (`--pre-push`)
+    pub pre_push: bool,
+    /// Signature handling (`--message-mode`); strip installs prepare-commit-msg
+    pub message_mode: Option<MessageMode>,
+}
+
+/// Install hagi's git hooks in the detected mode
+///
+/// The hooks are shims running `hagi hook run <name>`, which reads the hook
+/// rules from the project config on every run.
+pub fn install(project_dir: &Path, dry_run: bool, opts: &HookOptions) -> Result<()> {
+    let mode = detect_mode(project_dir)?;
+    let config = ProjectConfig::load(project_dir)?;
+    let pre_push = opts.pre_push || config.hooks.pre_push.unwrap_or(false);
+    let states = hook_states(project_dir, &mode)?;
+    let strip_installed = states.iter().any(|s| s.name == "prepare-commit-msg" && s.installed);
+    // Keep the current mode on reinstall unless a mode is given
+    let message_mode = opts
+        .message_mode
+        .or(config.hooks.message_mode)
+        .unwrap_or(if strip_installed { MessageMode::Strip } else { MessageMode::Reject });
+    let strip = message_mode == MessageMode::Strip;
+
+    let hooks: Vec<(&str, &str)> = GIT_HOOKS
+        .iter()
+        .filter(|(name, _)| match *name {
+            "pre-push" => pre_push,
+            "prepare-commit-msg" => strip,
+            _ => true,
+        })
+        .copied()
+        .collect();
+    println!("\n{} {}", "Installing git hooks:".green(), mode.to_string().dimmed());
+
+    if strip_installed && !strip {
+        let state = states.iter().find(|s| s.name == "prepare-commit-msg").expect("state listed above");
+        if dry_run {
+            println!("{} {}", "Would remove:".yellow(), state.path.display());
+        } else {
+            remove_hook(project_dir, &mode, state)?;
+            println!("  {} prepare-commit-msg (message mode: reject)", "Removed:".green());
+        }
+    }
+
+    match &mode {
+        HookMode::HooksDir { dir, .. } => install_dispatchers(dir, &hooks, dry_run)?,
+        HookMode::Husky => {
+            install_scripts(project_dir, &hooks, dry_run)?;
+            install_husky_blocks(project_dir, &hooks, dry_run)?;
+        }
+        HookMode::Lefthook => {
+            install_scripts(project_dir, &hooks, dry_run)?;
+            install_lefthook_local(project_dir, &hooks, dry_run)?;
+        }
+        HookMode::PreCommit => {
+            install_scripts(project_dir, &hooks, dry_run)?;
+            print_pre_commit_snippet(&hooks);
+        }
+    }
+
+    if !dry_run {
+        println!("  ✅ {}", "Git hooks installed".green());
+        println!(
+            "     {}",
+            format!(
+                "- pre-commit: Prevents direct commits to {}",
+                config.hooks.protected_branches().join(", ")
+            )
+            .dimmed()
+        );
+        if strip {
+            println!("     {}", "- prepare-commit-msg: Strips Claude Code signatures from the message".dimmed());
+        }
+        println!("     {}", "- commit-msg: Blocks Claude Code signatures and forbidden patterns".dimmed());
+        if config.hooks.subject_pattern.is_some() || config.hooks.subject_max_length.is_some() {
+            println!("     {}", "- commit-msg: Checks the commit subject format".dimmed());
+        }
+        if pre_push {
+            println!("     {}", "- pre-push: Blocks pushing commits with .claude/ files or signatures".dimmed());
+        }
+    }
+    Ok(())
+}
+
+/// Hooks that read from stdin (`pre-push` gets the pushed refs)
+const STDIN_HOOKS: &[&str] = &["pre-push"];
+
+/// Shell lines buffering stdin into `$hagi_stdin` so that two hooks can both read it
+///
+/// A chained hook (git-lfs, a husky command) would otherwise consume the input and
+/// leave hagi's check with nothing to inspect.
+const BUFFER_STDIN: &str = "hagi_stdin=\"$(mktemp)\" || exit 1\ncat > \"$hagi_stdin\"\n";
+
+/// Shell lines making the buffered input the script's stdin again (and removing the file)
+const RESTORE_STDIN: &str = "exec < \"$hagi_stdin\"\nrm -f \"$hagi_stdin\"\n";
+
+/// Hook content written to a hooks directory: the script with a prelude that
+/// first runs the hook it replaced (`<hook>.local`)
+fn dispatcher(name: &str, script: &str) -> String {
+    let (shebang, body) = script.split_once('\n').unwrap_or(("#!/bin/bash", script));
+    let header = format!("{shebang}\n{DISPATCHER_MARKER} (a previous hook, if any, is kept as <hook>{LOCAL_SUFFIX})\n");
+    if STDIN_HOOKS.contains(&name) {
+        return format!(
+            "{header}{BUFFER_STDIN}if [ -x \"$0{LOCAL_SUFFIX}\" ]; then\n    \
+             \"$0{LOCAL_SUFFIX}\" \"$@\" < \"$hagi_stdin\" || {{ hagi_status=$?; rm -f \"$hagi_stdin\"; exit $hagi_status; }}\n\
+             fi\n{RESTORE_STDIN}{body}"
+        );
+    }
+    format!("{header}if [ -x \"$0{LOCAL_SUFFIX}\" ]; then\n    \"$0{LOCAL_SUFFIX}\" \"$@\" || exit $?\nfi\n{body}")
+}
+
+/// Check if a hook was written by hagi (any version)
+fn is_hagi_hook(content: &str) -> bool {
+    content.lines().nth(1).is_some_and(|l| l.starts_with(DISPATCHER_MARKER))
+        || LEGACY_MARKERS.iter().any(|m| content.contains(m))
+}
+
+fn install_dispatchers(hooks_dir: &Path, hooks: &[(&str, &str)], dry_run: bool) -> Result<()> {
+    for (name, script) in hooks {
+        let hook_path = hooks_dir.join(name);
+        let local_path = hooks_dir.join(format!("{}{}", name, LOCAL_SUFFIX));
+        let existing = fs::read_to_string(&hook_path).ok();
+        let foreign = existing.as_deref().is_some_and(|c| !is_hagi_hook(c));
+
+        if dry_run {
+            if foreign {
+                println!("{} {} → {}", "Would chain:".yellow(), hook_path.display(), local_path.display());
+            }
+            println!("{} {}", "Would install:".yellow(), hook_path.display());
+            continue;
+        }
+
+        utils::ensure_dir(hooks_dir)?;
+        if foreign {
+            if local_path.exists() {
+                utils::backup_file(&local_path)?;
+            }
+            fs::rename(&hook_path, &local_path)
+                .with_context(|| format!("Failed to move {} aside", hook_path.display()))?;
+            println!("  {} existing {} → {}{}", "Chained:".green(), name, name, LOCAL_SUFFIX);
+        }
+        write_executable(&hook_path, &dispatcher(name, script))?;
+    }
+    Ok(())
+}
+
+/// Write the plain hook scripts to `<git-common-dir>/hagi/hooks/` (called by hook managers)
+fn install_scripts(project_dir: &Path, hooks: &[(&str, &str)], dry_run: bool) -> Result<()> {
+    let scripts_dir = scripts_dir(project_dir)?;
+    for (name, script) in hooks {
+        let path = scripts_dir.join(name);
+        if dry_run {
+            println!("{} {}", "Would install:".yellow(), path.display());
+        } else {
+            fs::create_dir_all(&scripts_dir)
+                .with_context(|| format!("Failed to create {}", scripts_dir.display()))?;
+            write_executable(&path, script)?;
+        }
+    }
+    Ok(())
+}
+
+/// Shell lines that run a hagi hook script, skipping it when the script is absent
+///
+/// For hooks reading stdin the block goes first in the file: it buffers the input,
+/// runs hagi on it and hands it on to the commands that follow.
+fn hook_call(name: &str) -> String {
+    let script = format!("hagi_hook=\"$(git rev-parse --git-common-dir)/{SCRIPTS_DIR}/{name}\"\n");
+    if STDIN_HOOKS.contains(&name) {
+        return format!(
+            "{BLOCK_START}\n{script}{BUFFER_STDIN}if [ -f \"$hagi_hook\" ]; then\n    \
+             sh \"$hagi_hook\" \"$@\" < \"$hagi_stdin\" || {{ hagi_status=$?; rm -f \"$hagi_stdin\"; exit $hagi_status; }}\n\
+             fi\n{RESTORE_STDIN}{BLOCK_END}\n"
+        );
+    }
+    format!("{BLOCK_START}\n{script}if [ -f \"$hagi_hook\" ]; then sh \"$hagi_hook\" \"$@\" || exit $?; fi\n{BLOCK_END}\n")
+}
+
+fn install_husky_blocks(project_dir: &Path, hooks: &[(&str, &str)], dry_run: bool) -> Result<()> {
+    for (name, _) in hooks {
+        let path = project_dir.join(".husky").join(name);
+        let existing = fs::read_to_string(&path).unwrap_or_default();
+        let updated = if STDIN_HOOKS.contains(name) {
+            prepend_block(&existing, &hook_call(name))
+        } else {
+            upsert_block(&existing, &hook_call(name))
+        };
+        if updated == existing {
+            continue;
+        }
+        if dry_run {
+            println!("{} .husky/{}", "Would register in:".yellow(), name);
+        } else {
+            write_executable(&path, &updated)?;
+            println!("  {} .husky/{}", "Registered in:".green(), name);
+        }
+    }
+    Ok(())
+}
+
+fn lefthook_block(hooks: &[(&str, &str)]) -> String {
+    let mut block = format!("{}\n", BLOCK_START);
+    for (name, _) in hooks {
+        let args = match *name {
+            "commit-msg" => " {1}",
+            "prepare-commit-msg" => " {1} {2} {3}",
+            _ => "",
+        };
+        // pre-push reads the pushed refs from stdin
+        let stdin = if *name == "pre-push" { "      use_stdin: true\n" } else { "" };
+        block.push_str(&format!(
+            "{name}:\n  commands:\n    hagi:\n      run: sh \"$(git rev-parse --git-common-dir)/{SCRIPTS_DIR}/{name}\"{args}\n{stdin}"
+        ));
+    }
+    block.push_str(BLOCK_END);
+    block.push('\n');
+    block
+}
+
+fn install_lefthook_local(project_dir: &Path, hooks: &[(&str, &str)], dry_run: bool) -> Result<()> {
+    let path = project_dir.join(LEFTHOOK_LOCAL);
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+
+    if !existing.trim().is_empty() && !existing.contains(BLOCK_START) {
+        // Merging into someone else's YAML is not safe without a parser
+        println!("  {} {} already exists; add this to it:", "⚠".yellow(), LEFTHOOK_LOCAL);
+        for line in lefthook_block(hooks).lines() {
+            println!("    {}", line);
+        }
+        return Ok(());
+    }
+
+    let updated = upsert_block(&existing, &lefthook_block(hooks));
+    if updated == existing {
+        return Ok(());
+    }
+    if dry_run {
+        println!("{} {}", "Would register in:".yellow(), LEFTHOOK_LOCAL);
+    } else {
+        fs::write(&path, updated).with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("  {} {} (run 'lefthook install' if hooks are not active)", "Registered in:".green(), LEFTHOOK_LOCAL);
+    }
+    Ok(())
+}
+
+fn print_pre_commit_snippet(hooks: &[(&str, &str)]) {
+    println!(
+        "  {} {} found; add this entry under 'repos:' to run hagi's checks:",
+        "⚠".yellow(),
+        PRE_COMMIT_CONFIG
+    );
+    let mut snippet = String::from("- repo: local\n  hooks:\n");
+    for (name, _) in hooks {
+        snippet.push_str(&format!(
+            "    - id: hagi-{name}\n      name: hagi {name}\n      \
+             entry: sh -c 'sh \"$(git rev-parse --git-common-dir)/{SCRIPTS_DIR}/{name}\" \"$@\"' --\n      \
+             language: system\n      always_run: true\n      pass_filenames: {}\n      stages: [{name}]\n",
+            matches!(*name, "commit-msg" | "prepare-commit-msg")
+        ));
+    }
+    for line in snippet.lines() {
+        println!("    {}", line);
+    }
+    let hook_types: Vec<String> = hooks.iter().map(|(name, _)| format!("--hook-type {}", name)).collect();
+    println!("  {}", format!("Then run: pre-commit install {}", hook_types.join(" ")).dimmed());
+}
+
+// ============================================================================
+// Status / Uninstall
+// ============================================================================
+
+/// State of one hagi hook in the repository
+#[derive(Debug)]
+pub struct HookState {
+    pub name: &'static str,
+    /// File holding the hook (or hagi's block)
+    pub path: PathBuf,
+    pub installed: bool,
+    /// Installed, but changed since hagi wrote it
+    pub modified: bool,
+    /// A chained `<hook>.local` exists
+    pub chained: bool,
+    /// Installed only on request (pre-push, prepare-commit-msg)
+    pub optional: bool,
+}
+
+/// Inspect hagi's hooks for the detected mode
+pub fn hook_states(project_dir: &Path, mode: &HookMode) -> Result<Vec<HookState>> {
+    let mut states = Vec::new();
+    for (name, script) in GIT_HOOKS {
+        let state = match mode {
+            HookMode::HooksDir { dir, .. } => {
+                let path = dir.join(name);
+                let content = fs::read_to_string(&path).ok();
+                HookState {
+                    name,
+                    installed: content.as_deref().is_some_and(is_hagi_hook),
+                    modified: content.as_deref().is_some_and(|c| is_hagi_hook(c) && c != dispatcher(name, script)),
+                    chained: dir.join(format!("{}{}", name, LOCAL_SUFFIX)).exists(),
+                    optional: OPTIONAL_HOOKS.contains(name),
+                    path,
+                }
+            }
+            HookMode::Husky => block_state(name, project_dir.join(".husky").join(name)),
+            HookMode::Lefthook => block_state(name, project_dir.join(LEFTHOOK_LOCAL)),
+            HookMode::PreCommit => {
+                let path = project_dir.join(PRE_COMMIT_CONFIG);
+                let registered = fs::read_to_string(&path).is_ok_and(|c| c.contains(&format!("hagi-{}", name)));
+                HookState {
+                    name,
+                    path,
+                    installed: registered,
+                    modified: false,
+                    chained: false,
+                    optional: OPTIONAL_HOOKS.contains(name),
+                }
+            }
+        };
+        states.push(state);
+    }
+    Ok(states)
+}
+
+fn block_state(name: &'static str, path: PathBuf) -> HookState {
+    // lefthook-local.yml holds one block for all hooks, so look for this hook's script in it
+    let installed = fs::read_to_string(&path)
+        .is_ok_and(|c| {
+            c.contains(BLOCK_START)
+                && (c.contains(&format!("{}/{}", SCRIPTS_DIR, name))
+                    // Written before scripts moved to the common dir: `$(git rev-parse --git-path hagi/hooks)/<name>`
+                    || c.contains(&format!("{})/{}", SCRIPTS_DIR, name)))
+        });
+    HookState { name, path, installed, modified: false, chained: false, optional: OPTIONAL_HOOKS.contains(&name) }
+}
+
+/// Remove one hook installed by hagi, restoring a chained `<hook>.local`
+pub fn remove_hook(project_dir: &Path, mode: &HookMode, state: &HookState) -> Result<()> {
+    match mode {
+        HookMode::HooksDir { dir, .. } => {
+            fs::remove_file(&state.path)
+                .with_context(|| format!("Failed to remove {}", state.path.display()))?;
+            let local_path = dir.join(format!("{}{}", state.name, LOCAL_SUFFIX));
+            if local_path.exists() {
+                fs::rename(&local_path, &state.path)
+                    .with_context(|| format!("Failed to restore {}", local_path.display()))?;
+                println!("{} Restored {} from {}{}", "✓".green(), state.name, state.name, LOCAL_SUFFIX);
+            }
+        }
+        // The shared lefthook block may already be gone with an earlier hook
+        HookMode::Husky | HookMode::Lefthook if state.path.exists() => {
+            let content = fs::read_to_string(&state.path)
+                .with_context(|| format!("Failed to read {}", state.path.display()))?;
+            let remaining = remove_block(&content);
+            if remaining.lines().all(|l| l.trim().is_empty() || l.starts_with("#!")) {
+                fs::remove_file(&state.path)
+                    .with_context(|| format!("Failed to remove {}", state.path.display()))?;
+            } else {
+                fs::write(&state.path, remaining)
+                    .with_context(|| format!("Failed to write {}", state.path.display()))?;
+            }
+        }
+        HookMode::Husky | HookMode::Lefthook => {}
+        HookMode::PreCommit => {
+            println!("  {} Remove the hagi-{} entry from {} manually", "⚠".yellow(), state.name, PRE_COMMIT_CONFIG);
+        }
+    }
+
+    if let Ok(scripts_dir) = scripts_dir(project_dir) {
+        let script = scripts_dir.join(state.name);
+        if script.exists() {
+            fs::remove_file(&script).with_context(|| format!("Failed to remove {}", script.display()))?;
+        }
+        // Remove <git-common-dir>/hagi/hooks and <git-common-dir>/hagi once empty
+        let _ = fs::remove_dir(&scripts_dir);
+        if let Some(parent) = scripts_dir.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+    }
+    Ok(())
+}
+
+/// Print the hook mode and per-hook state (used by `hagi status`)
+pub fn print_status(project_dir: &Path) -> Result<()> {
+    let mode = detect_mode(project_dir)?;
+    println!("  {} git hooks mode: {}", "ℹ".blue(), mode);
+
+    for state in hook_states(project_dir, &mode)? {
+        let display = state.path.strip_prefix(project_dir).unwrap_or(&state.path).display();
+        if !state.installed && state.optional {
+            let flag = match state.name {
+                "prepare-commit-msg" => "--message-mode strip",
+                _ => "--pre-push",
+            };
+            println!(
+                "    {} {} - {}",
+                "○".dimmed(),
+                state.name,
+                format!("optional, not installed (hagi install {})", flag).dimmed()
+            );
+            continue;
+        }
+        if !state.installed {
+            println!("    {} {} - {}", "✗".red(), state.name, format!("not installed ({})", display).dimmed());
+            continue;
+        }
+        let mut notes = vec![display.to_string()];
+        if state.chained {
+            notes.push(format!("chains {}{}", state.name, LOCAL_SUFFIX));
+        }
+        if state.modified {
+            notes.push("modified".to_string());
+        }
+        println!("    {} {} - {}", "✓".green(), state.name, notes.join(", ").dimmed());
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Replace hagi's marked block in `content`, or append it
+fn upsert_block(content: &str, block: &str) -> String {
+    if let (Some(start), Some(end)) = (content.find(BLOCK_START), content.find(BLOCK_END))
+        && start < end
+    {
+        let end = content[end..].find('\n').map(|i| end + i + 1).unwrap_or(content.len());
+        return format!("{}{}{}", &content[..start], block, &content[end..]);
+    }
+
+    let mut updated = content.to_string();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(block);
+    updated
+}
+
+/// Put hagi's marked block before any other command (after a shebang), replacing an existing one
+fn prepend_block(content: &str, block: &str) -> String {
+    let rest = remove_block(content);
+    match rest.strip_prefix("#!").and_then(|_| rest.split_once('\n')) {
+        Some((shebang, body)) => format!("{shebang}\n{block}{body}"),
+        None => format!("{block}{rest}"),
+    }
+}
+
+/// Remove hagi's marked block from `content`
+fn remove_block(content: &str) -> String {
+    let (Some(start), Some(end)) = (content.find(BLOCK_START), content.find(BLOCK_END)) else {
+        return content.to_string();
+    };
+    let end = content[end..].find('\n').map(|i| end + i + 1).unwrap_or(content.len());
+    format!("{}{}", &content[..start], &content[end..])
+}
+
+/// Directory for hook scripts called by hook managers (`<git-common-dir>/hagi/hooks`)
+///
+/// The common dir is shared by all worktrees; `--git-path hagi/hooks` would resolve to a
+/// per-worktree directory and leave the other worktrees without scripts.
+fn scripts_dir(project_dir: &Path) -> Result<PathBuf> {
+    let common_dir = utils::git_output(project_dir, &["rev-parse", "--git-common-dir"])
+        .context("Failed to resolve the git common directory (not a git repository?)")?;
+    Ok(project_dir.join(common_dir).join(SCRIPTS_DIR))
+}
+
+/// Resolve `git rev-parse --git-path <path>` against the project directory
+fn git_path(project_dir: &Path, path: &str) -> Result<PathBuf> {
+    let resolved = utils::git_output(project_dir, &["rev-parse", "--git-path", path])
+        .with_context(|| format!("Failed to resolve git path '{}' (not a git repository?)", path))?;
+    Ok(project_dir.join(resolved))
+}
+
+fn write_executable(path: &Path, content: &str) -> Result<()> {
+    fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(path, perms)
+            .with_context(|| format!("Failed to make {} executable", path.display()))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_upsert_and_remove_block() {
+        let original = "#!/bin/sh\nnpm test\n";
+        let added = upsert_block(original, &hook_call("pre-commit"));
+        assert!(added.starts_with(original));
+        assert_eq!(upsert_block(&added, &hook_call("pre-commit")), added);
+        assert_eq!(remove_block(&added), original);
+    }
+
+    #[test]
+    fn test_linked_worktree_uses_common_hooks_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let main = tmp.path().join("main");
+        let linked = tmp.path().join("linked");
+        utils::init_test_repo(&main);
+        utils::git(&main, &["worktree", "add", "-q", linked.to_str().unwrap()]).unwrap();
+
+        let common = main.join(".git").canonicalize().unwrap();
+        let HookMode::HooksDir { dir, custom: false } = detect_mode(&linked).unwrap() else {
+            panic!("expected the plain hooks directory");
+        };
+        assert_eq!(dir.canonicalize().unwrap(), common.join("hooks"));
+        let scripts = scripts_dir(&linked).unwrap();
+        assert!(scripts.ends_with(SCRIPTS_DIR));
+        assert_eq!(scripts.ancestors().nth(2).unwrap().canonicalize().unwrap(), common);
+
+        install(&linked, false, &HookOptions::default()).unwrap();
+        let hook = fs::read_to_string(common.join("hooks/pre-commit")).unwrap();
+        assert!(is_hagi_hook(&hook));
+    }
+
+    #[test]
+    fn test_dispatcher_is_recognised() {
+        let (_, script) = GIT_HOOKS[0];
+        assert!(is_hagi_hook(&dispatcher(GIT_HOOKS[0].0, script)));
+        assert!(!is_hagi_hook("#!/bin/sh\nnpm run lint\n"));
+    }
+
+    /// Run the `pre-push` hook with `input` on stdin, next to `files` (hooks write what they read)
+    #[cfg(unix)]
+    fn run_pre_push(files: &[(&str, &str)], input: &str) -> (String, String) {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let mut child = Command::new("sh")
+            .arg(dir.path().join("pre-push"))
+            .current_dir(dir.path())
+            .stdin(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        assert!(child.wait().unwrap().success());
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap_or_default();
+        (read("seen-by-local"), read("seen-by-hagi"))
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_pre_push_input_reaches_chained_and_hagi_hooks() {
+        let input = "refs/heads/main 1111 refs/heads/main 2222\n";
+        let hagi = "#!/bin/sh\ncat > seen-by-hagi\n";
+
+        // Dispatcher: the chained pre-push.local (e.g. git-lfs) reads the refs first
+        let hook = dispatcher("pre-push", hagi);
+        let files = [("pre-push", hook.as_str()), ("pre-push.local", "#!/bin/sh\ncat > seen-by-local\n")];
+        assert_eq!(run_pre_push(&files, input), (input.to_string(), input.to_string()));
+
+        // husky: hagi's block runs first, the commands after it still get the refs
+        let husky = prepend_block("#!/bin/sh\ncat > seen-by-local\n", &hook_call("pre-push"))
+            .replace(&format!("$(git rev-parse --git-common-dir)/{}/pre-push", SCRIPTS_DIR), "./hagi-pre-push");
+        let files = [("pre-push", husky.as_str()), ("hagi-pre-push", hagi)];
+        assert_eq!(run_pre_push(&files, input), (input.to_string(), input.to_string()));
+    }
+}
@@ -0,0 +1,742 @@
+use anyhow::{bail, Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use crate::utils;
+
+/// Command Claude Code runs for the PreToolUse hook
+pub const GUARD_COMMAND: &str = "hagi guard pretooluse";
+
+/// Shell hook the guard replaces (jq-based, shipped by earlier versions)
+pub const LEGACY_SCRIPT: &str = "hooks/check-claude-git.sh";
+
+/// Directory the guard protects
+const CLAUDE_DIR: &str = ".claude";
+
+/// Guard rules file (in the project `.claude/` and in `~/.claude/`)
+pub const RULES_FILE: &str = "guard.toml";
+
+/// Name reported for the built-in `.claude/` git check
+const BUILTIN_RULE: &str = "builtin: .claude/ git protection";
+
+/// `tool_input` fields holding the file a tool works on
+const PATH_FIELDS: &[&str] = &["file_path", "path", "notebook_path"];
+
+/// git options that take the following argument as their value
+const GIT_VALUE_OPTIONS: &[&str] = &["-C", "-c", "--git-dir", "--work-tree", "--namespace", "--config-env"];
+
+/// `git commit` options that take the following argument as their value
+const COMMIT_VALUE_OPTIONS: &[&str] = &[
+    "-m", "-F", "-c", "-C", "-t", "--message", "--file", "--reedit-message", "--reuse-message",
+    "--template", "--author", "--date", "--fixup", "--squash", "--cleanup", "--trailer",
+    "--pathspec-from-file",
+];
+
+/// Short `git commit` options whose value may be attached (`-mmsg`)
+const COMMIT_SHORT_VALUE_FLAGS: &[char] = &['m', 'F', 'c', 'C', 't', 'u', 'S'];
+
+/// Claude Code PreToolUse hook input (fields hagi uses)
+#[derive(Debug, Deserialize)]
+pub struct HookPayload {
+    pub tool_name: String,
+    #[serde(default)]
+    pub tool_input: Value,
+    /// Working directory of the session (relative tool paths are resolved against it)
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+}
+
+impl HookPayload {
+    pub fn parse(input: &str) -> Result<Self> {
+        serde_json::from_str(input).context("Failed to parse PreToolUse hook input")
+    }
+
+    /// Shell command of a Bash tool call
+    pub fn command(&self) -> Option<&str> {
+        self.tool_input.get("command").and_then(|c| c.as_str())
+    }
+
+    /// File or directory the tool works on, made absolute against `cwd`
+    pub fn path(&self, project_dir: &Path) -> Option<PathBuf> {
+        let path = PATH_FIELDS
+            .iter()
+            .find_map(|field| self.tool_input.get(field).and_then(|p| p.as_str()))?;
+        let base = self.cwd.as_deref().unwrap_or(project_dir);
+        Some(normalize(&base.join(path)))
+    }
+}
+
+/// What to do with a tool call, from least to most restrictive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Allow,
+    Ask,
+    Deny,
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Allow => write!(f, "allow"),
+            Action::Ask => write!(f, "ask"),
+            Action::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// Decision for a tool call and the rule that made it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decision {
+    pub action: Action,
+    pub reason: String,
+    /// Rule name and the file it comes from
+    pub rule: String,
+}
+
+impl Decision {
+    /// Hook output JSON Claude Code expects from a PreToolUse hook
+    pub fn to_hook_output(&self) -> Value {
+        json!({
+            "hookSpecificOutput": {
+                "hookEventName": "PreToolUse",
+                "permissionDecision": self.action.to_string(),
+                "permissionDecisionReason": self.reason,
+            }
+        })
+    }
+}
+
+// ============================================================================
+// Rules
+// ============================================================================
+
+/// A guard rules file
+///
+/// ```toml
+/// [[rules]]
+/// name = "env-files"
+/// tool = "Read|Edit|Write"
+/// path = ".env*"
+/// action = "deny"
+/// reason = "Secrets stay out of the session"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    /// Run the built-in `.claude/` git check (default: true)
+    builtin: Option<bool>,
+    #[serde(default)]
+    rules: Vec<RuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    name: Option<String>,
+    /// Regex matched against the whole tool name (`Bash`, `Read|Edit`, `mcp__.*`)
+    tool: Option<String>,
+    /// Regex searched in the Bash command
+    command: Option<String>,
+    /// Glob matched against the tool's file path
+    path: Option<String>,
+    action: Action,
+    #[serde(default)]
+    reason: String,
+}
+
+#[derive(Debug)]
+struct Rule {
+    label: String,
+    tool: Option<Regex>,
+    command: Option<Regex>,
+    path: Option<GlobMatcher>,
+    action: Action,
+    reason: String,
+}
+
+impl Rule {
+    fn matches(&self, payload: &HookPayload, project_dir: &Path) -> bool {
+        if let Some(tool) = &self.tool
+            && !tool.is_match(&payload.tool_name)
+        {
+            return false;
+        }
+        if let Some(command) = &self.command
+            && !payload.command().is_some_and(|c| command.is_match(c))
+        {
+            return false;
+        }
+        if let Some(path) = &self.path
+            && !payload.path(project_dir).is_some_and(|p| path.is_match(p))
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Guard rules from the project and global rules files
+///
+/// Every matching rule is considered and the most restrictive action wins
+/// (deny > ask > allow); among equals the first rule (project before global) is reported.
+#[derive(Debug)]
+pub struct GuardRules {
+    builtin: bool,
+    rules: Vec<Rule>,
+    project_dir: PathBuf,
+}
+
+impl GuardRules {
+    /// Load `<project>/.claude/guard.toml`, then `~/.claude/guard.toml`
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let mut files = vec![project_dir.join(CLAUDE_DIR).join(RULES_FILE)];
+        if let Ok(global_dir) = utils::claude_dir() {
+            let global = global_dir.join(RULES_FILE);
+            if !files.contains(&global) {
+                files.push(global);
+            }
+        }
+
+        let mut builtin = None;
+        let mut rules = Vec::new();
+        for path in files.iter().filter(|p| p.exists()) {
+            let content = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let file: RulesFile = toml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            builtin = builtin.or(file.builtin);
+            for (index, spec) in file.rules.into_iter().enumerate() {
+                let rule = compile_rule(spec, index, path, project_dir)
+                    .with_context(|| format!("Invalid rule in {}", path.display()))?;
+                rules.push(rule);
+            }
+        }
+
+        Ok(Self {
+            builtin: builtin.unwrap_or(true),
+            rules,
+            project_dir: project_dir.to_path_buf(),
+        })
+    }
+
+    /// Decide on a tool call (None = no rule applies)
+    pub fn evaluate(&self, payload: &HookPayload) -> Option<Decision> {
+        let builtin = self.builtin.then(|| check_payload(payload)).flatten();
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(payload, &self.project_dir))
+            .map(|rule| Decision {
+                action: rule.action,
+                reason: rule.reason.clone(),
+                rule: rule.label.clone(),
+            })
+            .chain(builtin)
+            .fold(None, |best: Option<Decision>, decision| match best {
+                Some(best) if best.action >= decision.action => Some(best),
+                _ => Some(decision),
+            })
+    }
+}
+
+fn compile_rule(spec: RuleSpec, index: usize, source: &Path, project_dir: &Path) -> Result<Rule> {
+    let name = spec.name.unwrap_or_else(|| format!("rules[{}]", index));
+    if spec.tool.is_none() && spec.command.is_none() && spec.path.is_none() {
+        bail!("Rule '{}' needs at least one of: tool, command, path", name);
+    }
+
+    let tool = spec
+        .tool
+        .map(|t| Regex::new(&format!("^(?:{})$", t)).with_context(|| format!("Invalid tool pattern: '{}'", t)))
+        .transpose()?;
+    let command = spec
+        .command
+        .map(|c| Regex::new(&c).with_context(|| format!("Invalid command pattern: '{}'", c)))
+        .transpose()?;
+    let path = spec.path.map(|p| compile_path_glob(&p, project_dir)).transpose()?;
+    let reason = if spec.reason.is_empty() {
+        format!("Blocked by guard rule '{}'", name)
+    } else {
+        spec.reason
+    };
+
+    Ok(Rule {
+        label: format!("{} ({})", name, source.display()),
+        tool,
+        command,
+        path,
+        action: spec.action,
+        reason,
+    })
+}
+
+/// Compile a path glob: `~/` is the home directory, patterns without `/` match a
+/// file name anywhere, other relative patterns are relative to the project
+fn compile_path_glob(pattern: &str, project_dir: &Path) -> Result<GlobMatcher> {
+    let expanded = if let Some(rest) = pattern.strip_prefix("~/") {
+        utils::home_dir()?.join(rest).to_string_lossy().to_string()
+    } else if !pattern.contains('/') {
+        format!("**/{}", pattern)
+    } else if pattern.starts_with('/') || pattern.starts_with("**") {
+        pattern.to_string()
+    } else {
+        normalize(project_dir).join(pattern).to_string_lossy().to_string()
+    };
+
+    GlobBuilder::new(&expanded)
+        .literal_separator(true)
+        .build()
+        .map(|g| g.compile_matcher())
+        .with_context(|| format!("Invalid path pattern: '{}'", pattern))
+}
+
+/// Lexically normalize a path (resolve `.` and `..` without touching the filesystem)
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+// ============================================================================
+// Settings Migration
+// ============================================================================
+
+/// Point PreToolUse hook entries running the legacy jq script at the guard
+///
+/// Used on settings files installed by earlier versions; returns whether anything changed.
+pub fn replace_legacy_script(settings: &mut Value) -> bool {
+    let Some(pre_tool_use) = settings.pointer_mut("/hooks/PreToolUse").and_then(|p| p.as_array_mut()) else {
+        return false;
+    };
+    let mut changed = false;
+    for hook in pre_tool_use
+        .iter_mut()
+        .filter_map(|entry| entry.get_mut("hooks").and_then(|h| h.as_array_mut()))
+        .flatten()
+    {
+        if hook.get("command").and_then(|c| c.as_str()).is_some_and(|c| c.contains(LEGACY_SCRIPT)) {
+            hook["command"] = json!(GUARD_COMMAND);
+            changed = true;
+        }
+    }
+    changed
+}
+
+// ============================================================================
+// Built-in .claude/ Git Check
+// ============================================================================
+
+/// Built-in check: git commands that would put `.claude/` into the project repository
+pub fn check_payload(payload: &HookPayload) -> Option<Decision> {
+    if payload.tool_name != "Bash" {
+        return None;
+    }
+    check_command(payload.command()?).map(|reason| Decision {
+        action: Action::Deny,
+        reason,
+        rule: BUILTIN_RULE.to_string(),
+    })
+}
+
+/// Reason to block a shell command, if it runs git in a way that touches `.claude/`
+pub fn check_command(command: &str) -> Option<String> {
+    let mut dir = PathBuf::new();
+
+    for words in split_commands(command) {
+        let words = skip_prefixes(&words);
+        match words.first().map(String::as_str) {
+            Some("cd") => {
+                match words.get(1) {
+                    Some(target) if !target.starts_with('-') => dir = join(&dir, target),
+                    _ => dir = PathBuf::new(),
+                }
+            }
+            Some("git") => {
+                if let Some(reason) = check_git(&words[1..], &dir) {
+                    return Some(reason);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Check one git invocation (arguments after `git`)
+fn check_git(args: &[String], dir: &Path) -> Option<String> {
+    let mut dir = dir.to_path_buf();
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        let arg = &args[i];
+        if arg == "-C" {
+            dir = join(&dir, args.get(i + 1)?);
+        }
+        i += if GIT_VALUE_OPTIONS.contains(&arg.as_str()) { 2 } else { 1 };
+    }
+    let subcommand = args.get(i)?.as_str();
+    let rest = &args[i + 1..];
+
+    if matches!(subcommand, "add" | "stage" | "commit") && touches_claude(&dir) {
+        return Some(format!(
+            "git {} runs inside {}/ ({}/ is synced with 'hagi sync', not the project repository)",
+            subcommand, CLAUDE_DIR, CLAUDE_DIR
+        ));
+    }
+
+    match subcommand {
+        "add" | "stage" => check_add(rest, &dir),
+        "commit" => check_commit(rest, &dir),
+        _ => None,
+    }
+}
+
+fn check_add(args: &[String], dir: &Path) -> Option<String> {
+    let mut after_dashdash = false;
+    for arg in args {
+        if !after_dashdash && arg == "--" {
+            after_dashdash = true;
+        } else if !after_dashdash && arg.starts_with('-') {
+            if matches!(arg.as_str(), "-A" | "--all" | "-u" | "--update") {
+                return Some(broad_add_reason(arg));
+            }
+        } else if is_broad_pathspec(arg) {
+            return Some(broad_add_reason(arg));
+        } else if touches_claude(&join(dir, arg)) {
+            return Some(claude_path_reason("add", arg));
+        }
+    }
+    None
+}
+
+fn check_commit(args: &[String], dir: &Path) -> Option<String> {
+    let mut after_dashdash = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        i += 1;
+        if !after_dashdash && arg == "--" {
+            after_dashdash = true;
+            continue;
+        }
+        if !after_dashdash && arg.starts_with("--") {
+            if arg == "--all" {
+                return Some(commit_all_reason(arg));
+            }
+            if COMMIT_VALUE_OPTIONS.contains(&arg.as_str()) {
+                i += 1;
+            }
+            continue;
+        }
+        if !after_dashdash && arg.starts_with('-') && arg.len() > 1 {
+            // Short option cluster: `-am msg`, `-a`, `-mmsg`
+            for (pos, c) in arg.bytes().map(char::from).enumerate().skip(1) {
+                if c == 'a' {
+                    return Some(commit_all_reason(arg));
+                }
+                if COMMIT_SHORT_VALUE_FLAGS.contains(&c) {
+                    // The value is the rest of the cluster, or the next argument
+                    if pos + 1 == arg.len() && c != 'u' && c != 'S' {
+                        i += 1;
+                    }
+                    break;
+                }
+            }
+            continue;
+        }
+        if touches_claude(&join(dir, arg)) {
+            return Some(claude_path_reason("commit", arg));
+        }
+    }
+    None
+}
+
+fn broad_add_reason(arg: &str) -> String {
+    format!(
+        "'git add {}' may stage {}/ files. Stage explicit paths instead ({}/ is synced with 'hagi sync', not git)",
+        arg, CLAUDE_DIR, CLAUDE_DIR
+    )
+}
+
+fn commit_all_reason(arg: &str) -> String {
+    format!(
+        "'git commit {}' may commit tracked {}/ files. Stage explicit paths and commit without -a",
+        arg, CLAUDE_DIR
+    )
+}
+
+fn claude_path_reason(subcommand: &str, path: &str) -> String {
+    format!(
+        "'git {} {}' touches {}/, which must not be committed ({}/ is synced with 'hagi sync', not git)",
+        subcommand, path, CLAUDE_DIR, CLAUDE_DIR
+    )
+}
+
+/// Pathspecs that match the whole tree (`.`, `:/`, `*`, ...)
+fn is_broad_pathspec(arg: &str) -> bool {
+    matches!(arg, "." | "./" | ":/" | ":" | "*" | ":/*" | "./*")
+}
+
+/// Check if a (lexically normalized) path is `.claude` or inside it
+fn touches_claude(path: &Path) -> bool {
+    let mut normalized: Vec<&std::ffi::OsStr> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(name) => normalized.push(name),
+            _ => {}
+        }
+    }
+    normalized.iter().any(|c| *c == CLAUDE_DIR || glob_matches_claude(&c.to_string_lossy()))
+}
+
+/// Check if a glob pathspec component (`.cl*`) matches `.claude`
+fn glob_matches_claude(name: &str) -> bool {
+    name.contains(['*', '?', '['])
+        && globset::Glob::new(name).is_ok_and(|g| g.compile_matcher().is_match(CLAUDE_DIR))
+}
+
+fn join(dir: &Path, path: &str) -> PathBuf {
+    // Pathspec magic like `:(top).claude` or `:/.claude` is relative to the repository root
+    let path = path.strip_prefix(":/").or_else(|| path.strip_prefix(":(top)")).unwrap_or(path);
+    dir.join(path)
+}
+
+/// Drop leading `VAR=value` assignments and wrappers such as `sudo`, `env`, `command`
+fn skip_prefixes(words: &[String]) -> &[String] {
+    let mut i = 0;
+    while let Some(word) = words.get(i) {
+        let is_assignment = word.split_once('=').is_some_and(|(name, _)| {
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        });
+        if is_assignment || matches!(word.as_str(), "sudo" | "env" | "command" | "exec" | "time" | "nohup") {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    &words[i..]
+}
+
+/// Split a shell command line into simple commands (word lists)
+///
+/// Handles quotes, backslash escapes and the `;`, `&&`, `||`, `|`, `&`, newline
+/// separators. Subshell parentheses and braces are treated as separators too.
+fn split_commands(command: &str) -> Vec<Vec<String>> {
+    let mut commands = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_word = true;
+                for c in chars.by_ref() {
+                    if c == '\'' {
+                        break;
+                    }
+                    word.push(c);
+                }
+            }
+            '"' => {
+                in_word = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            if let Some(next) = chars.next() {
+                                word.push(next);
+                            }
+                        }
+                        _ => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                if let Some(next) = chars.next()
+                    && next != '\n'
+                {
+                    in_word = true;
+                    word.push(next);
+                }
+            }
+            ';' | '&' | '|' | '\n' => {
+                finish_word(&mut word, &mut in_word, &mut words);
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            // Grouping and command substitution; `(`/`)` inside a word is pathspec magic (`:(top)`)
+            '(' | ')' | '{' | '}' if !in_word || (c == '(' && word == "$") || (c == ')' && !word.contains('(')) => {
+                if word == "$" {
+                    word.clear();
+                    in_word = false;
+                }
+                finish_word(&mut word, &mut in_word, &mut words);
+                if !words.is_empty() {
+                    commands.push(std::mem::take(&mut words));
+                }
+            }
+            c if c.is_whitespace() => finish_word(&mut word, &mut in_word, &mut words),
+            _ => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    finish_word(&mut word, &mut in_word, &mut words);
+    if !words.is_empty() {
+        commands.push(words);
+    }
+    commands
+}
+
+fn finish_word(word: &mut String, in_word: &mut bool, words: &mut Vec<String>) {
+    if *in_word {
+        words.push(std::mem::take(word));
+        *in_word = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(toml: &str) -> GuardRules {
+        let file: RulesFile = toml::from_str(toml).unwrap();
+        let project_dir = Path::new("/work/app");
+        GuardRules {
+            builtin: file.builtin.unwrap_or(true),
+            rules: file
+                .rules
+                .into_iter()
+                .enumerate()
+                .map(|(i, spec)| compile_rule(spec, i, Path::new("guard.toml"), project_dir).unwrap())
+                .collect(),
+            project_dir: project_dir.to_path_buf(),
+        }
+    }
+
+    fn payload(json: &str) -> HookPayload {
+        HookPayload::parse(json).unwrap()
+    }
+
+    #[test]
+    fn test_rules_most_restrictive_wins() {
+        let rules = rules(
+            r#"
+            [[rules]]
+            name = "git-ok"
+            tool = "Bash"
+            command = "^git "
+            action = "allow"
+
+            [[rules]]
+            name = "force-push"
+            command = "git push .*(--force|-f)"
+            action = "ask"
+            reason = "Force push"
+            "#,
+        );
+        let decide = |command: &str| {
+            let json = json!({"tool_name": "Bash", "tool_input": {"command": command}}).to_string();
+            rules.evaluate(&payload(&json)).map(|d| (d.action, d.rule))
+        };
+        assert_eq!(decide("git status").unwrap().0, Action::Allow);
+        assert_eq!(decide("git push --force").unwrap().0, Action::Ask);
+        assert_eq!(decide("git add -A").unwrap(), (Action::Deny, BUILTIN_RULE.to_string()));
+        assert_eq!(decide("ls"), None);
+    }
+
+    #[test]
+    fn test_rules_path_globs() {
+        let rules = rules(
+            r#"
+            builtin = false
+
+            [[rules]]
+            tool = "Read|Edit"
+            path = ".env*"
+            action = "deny"
+
+            [[rules]]
+            path = "config/*.toml"
+            action = "ask"
+            "#,
+        );
+        let decide = |tool: &str, path: &str| {
+            let json = json!({"tool_name": tool, "tool_input": {"file_path": path}, "cwd": "/work/app"});
+            rules.evaluate(&payload(&json.to_string())).map(|d| d.action)
+        };
+        assert_eq!(decide("Read", "/work/app/sub/.env.local"), Some(Action::Deny));
+        assert_eq!(decide("Write", ".env"), None);
+        assert_eq!(decide("Edit", "config/app.toml"), Some(Action::Ask));
+        assert_eq!(decide("Edit", "config/nested/app.toml"), None);
+    }
+
+    #[test]
+    fn test_replace_legacy_script() {
+        let mut settings = json!({"hooks": {"PreToolUse": [{"matcher": "Bash", "hooks": [
+            {"type": "command", "command": ".claude/hooks/check-claude-git.sh"},
+            {"type": "command", "command": "npm run lint"}
+        ]}]}});
+        assert!(replace_legacy_script(&mut settings));
+        assert_eq!(settings["hooks"]["PreToolUse"][0]["hooks"][0]["command"], GUARD_COMMAND);
+        assert_eq!(settings["hooks"]["PreToolUse"][0]["hooks"][1]["command"], "npm run lint");
+        assert!(!replace_legacy_script(&mut settings));
+        assert!(!replace_legacy_script(&mut json!({"permissions": {}})));
+    }
+
+    #[test]
+    fn test_blocks_claude_git_commands() {
+        for command in [
+            "git add .claude/settings.local.json",
+            "git add -A",
+            "git add .",
+            "git commit -am 'update'",
+            "git commit --all -m x",
+            "git -C sub add ../.claude/x",
+            "git -C .claude commit -m sync",
+            "cd .claude && git add TODO.md",
+            "FOO=1 git add ./.claude",
+            "git status && git add \".claude/a b.md\"",
+            "echo $(git add -A)",
+            "git add ':(top).claude'",
+            "git add .cl*",
+        ] {
+            assert!(check_command(command).is_some(), "not blocked: {}", command);
+        }
+    }
+
+    #[test]
+    fn test_allows_unrelated_commands() {
+        for command in [
+            "git add src/main.rs",
+            "git commit -m 'add .claude notes to docs'",
+            "git commit -F msg.txt",
+            "git commit -ém .claude/notes",
+            "git status .claude",
+            "git rm --cached -r .claude",
+            "echo 'git add -A'",
+            "cd .claude && ls",
+        ] {
+            assert_eq!(check_command(command), None, "blocked: {}", command);
+        }
+    }
+}
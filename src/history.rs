@@ -0,0 +1,115 @@
+use anyhow::{bail, Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::rules::{self, CommitRules, MessageViolation};
+
+/// A commit that adds `.claude/` files or carries a forbidden pattern
+#[derive(Debug, Clone)]
+pub struct Offender {
+    pub commit: String,
+    pub subject: String,
+    /// `.claude/` paths added or modified by the commit
+    pub claude_paths: Vec<String>,
+    /// Message lines matching a forbidden pattern
+    pub signatures: Vec<String>,
+}
+
+impl Offender {
+    pub fn short(&self) -> &str {
+        &self.commit[..self.commit.len().min(12)]
+    }
+}
+
+/// Scan the commits selected by `git log <revisions>` with the hook rules
+///
+/// Deletions are ignored, so commits removing `.claude/` from the index are not reported.
+pub fn scan(project_dir: &Path, rules: &CommitRules, revisions: &[&str]) -> Result<Vec<Offender>> {
+    // No --diff-filter: it would also drop commits without matching changes (e.g. empty ones)
+    let mut args = vec!["log", "--format=%x00%H%x1f%B%x1f", "--name-status", "--no-renames"];
+    args.extend_from_slice(revisions);
+    let output = Command::new("git")
+        .args(&args)
+        .current_dir(project_dir)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut offenders = Vec::new();
+    for record in stdout.split('\0').filter(|r| !r.trim().is_empty()) {
+        let mut fields = record.splitn(3, '\x1f');
+        let (Some(commit), Some(message), files) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+
+        let claude_paths: Vec<String> = files
+            .unwrap_or("")
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter(|(status, path)| *status != "D" && rules::is_claude_path(path))
+            .map(|(_, path)| path.to_string())
+            .collect();
+        let signatures: Vec<String> = rules
+            .forbidden_in(message)
+            .into_iter()
+            .filter_map(|v| match v {
+                MessageViolation::Forbidden { line, .. } => Some(line),
+                _ => None,
+            })
+            .collect();
+
+        if !claude_paths.is_empty() || !signatures.is_empty() {
+            offenders.push(Offender {
+                commit: commit.trim().to_string(),
+                subject: message.lines().next().unwrap_or("").to_string(),
+                claude_paths,
+                signatures,
+            });
+        }
+    }
+    Ok(offenders)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project_config::HooksConfig;
+    use crate::utils;
+    use std::fs;
+
+    #[test]
+    fn test_scan_reports_claude_files_and_signatures() {
+        let repo = tempfile::tempdir().unwrap();
+        let dir = repo.path();
+        utils::init_test_repo(dir);
+        fs::create_dir(dir.join(".claude")).unwrap();
+        fs::write(dir.join(".claude/settings.json"), "{}").unwrap();
+        utils::git(dir, &["add", "-f", ".claude/settings.json"]).unwrap();
+        utils::git(dir, &["commit", "-qm", "Add settings"]).unwrap();
+        utils::git(dir, &["commit", "-q", "--allow-empty", "-m", "Fix bug\n\nCo-Authored-By: Claude <noreply@example.com>"]).unwrap();
+        utils::git(dir, &["rm", "-q", "--cached", ".claude/settings.json"]).unwrap();
+        utils::git(dir, &["commit", "-qm", "Stop tracking .claude/"]).unwrap();
+
+        let rules = CommitRules::new(&HooksConfig::default()).unwrap();
+        let offenders = scan(dir, &rules, &["HEAD"]).unwrap();
+        let found: Vec<(&str, &[String], &[String])> = offenders
+            .iter()
+            .map(|o| (o.subject.as_str(), o.claude_paths.as_slice(), o.signatures.as_slice()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Fix bug", &[][..], &["Co-Authored-By: Claude <noreply@example.com>".to_string()][..]),
+                ("Add settings", &[".claude/settings.json".to_string()][..], &[][..]),
+            ]
+        );
+        assert_eq!(offenders[0].short().len(), 12);
+    }
+}
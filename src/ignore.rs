@@ -0,0 +1,250 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+/// Entries `hagi install` adds to the project's ignore file
+///
+/// `/.claude` has no trailing slash so that it also matches the symlink of a shared `.claude/`.
+pub const IGNORE_ENTRIES: &[&str] = &["/.claude", "/.mcp.json", "/.serena/"];
+
+/// Entries written by earlier versions (migrated into the block, removed on uninstall)
+const LEGACY_ENTRIES: &[&str] = &["/.claude/"];
+
+/// Markers around the block hagi owns in an ignore file
+const BLOCK_START: &str = "# >>> hagi >>>";
+const BLOCK_END: &str = "# <<< hagi <<<";
+
+/// Where the ignore entries are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IgnoreMode {
+    /// The tracked `.gitignore` at the project root
+    #[default]
+    Gitignore,
+    /// The repository-local `info/exclude` (never committed)
+    Exclude,
+}
+
+impl IgnoreMode {
+    pub const ALL: [IgnoreMode; 2] = [IgnoreMode::Gitignore, IgnoreMode::Exclude];
+
+    /// Ignore file for this mode (`info/exclude` is resolved with `git rev-parse --git-path`)
+    pub fn path(&self, project_dir: &Path) -> Result<PathBuf> {
+        match self {
+            IgnoreMode::Gitignore => Ok(project_dir.join(".gitignore")),
+            IgnoreMode::Exclude => {
+                let output = Command::new("git")
+                    .args(["rev-parse", "--git-path", "info/exclude"])
+                    .current_dir(project_dir)
+                    .output()
+                    .context("Failed to run git")?;
+                if !output.status.success() {
+                    bail!("Not a git repository; --ignore-mode exclude needs one");
+                }
+                Ok(project_dir.join(String::from_utf8_lossy(&output.stdout).trim()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for IgnoreMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IgnoreMode::Gitignore => write!(f, "gitignore"),
+            IgnoreMode::Exclude => write!(f, "exclude"),
+        }
+    }
+}
+
+impl FromStr for IgnoreMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "gitignore" => Ok(IgnoreMode::Gitignore),
+            "exclude" => Ok(IgnoreMode::Exclude),
+            _ => bail!("Unknown ignore mode: '{}'\nValid modes: gitignore, exclude", s),
+        }
+    }
+}
+
+/// hagi's entries present in an ignore file (in the block or as loose lines)
+pub fn present_entries(path: &Path) -> Vec<&'static str> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    IGNORE_ENTRIES
+        .iter()
+        .chain(LEGACY_ENTRIES)
+        .filter(|entry| content.lines().any(|line| line.trim() == **entry))
+        .copied()
+        .collect()
+}
+
+/// Write hagi's block to the ignore file of `mode`, removing hagi's lines from the other file
+pub fn install(project_dir: &Path, mode: IgnoreMode, dry_run: bool) -> Result<()> {
+    let path = mode.path(project_dir)?;
+    let display = display_path(project_dir, &path);
+
+    for other in IgnoreMode::ALL.into_iter().filter(|m| *m != mode) {
+        let Ok(other_path) = other.path(project_dir) else {
+            continue;
+        };
+        if present_entries(&other_path).is_empty() {
+            continue;
+        }
+        if dry_run {
+            println!("{} {} → {}", "Would move entries:".yellow(), display_path(project_dir, &other_path), display);
+        } else {
+            uninstall(&other_path)?;
+            println!("{} {} → {}", "Moved entries:".green(), display_path(project_dir, &other_path), display);
+        }
+    }
+
+    let existing = if path.exists() {
+        fs::read_to_string(&path).with_context(|| format!("Failed to read {}", display))?
+    } else {
+        String::new()
+    };
+    let updated = upsert_block(&existing);
+    if updated == existing {
+        println!("{} {} (no changes needed)", "Checked:".blue(), display);
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("{} {}", "Would update:".yellow(), display);
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, &updated).with_context(|| format!("Failed to write {}", display))?;
+    println!("{} {}", "Updated:".green(), display);
+    for entry in IGNORE_ENTRIES {
+        if !existing.lines().any(|line| line.trim() == *entry) {
+            println!("  {} {}", "+".green(), entry);
+        }
+    }
+    Ok(())
+}
+
+/// Remove hagi's block (and loose entries from older versions), keeping everything else as-is
+pub fn uninstall(path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    fs::write(path, remove_block(&content)).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Path relative to the project when inside it (`.git/info/exclude`), otherwise absolute
+pub fn display_path(project_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(project_dir).unwrap_or(path).display().to_string()
+}
+
+/// Lines of `content` outside hagi's block, without loose hagi entries
+///
+/// A start marker without an end marker (edited by hand) does not claim the rest of
+/// the file: only hagi's own entries are dropped after it.
+fn user_lines(content: &str) -> (Vec<&str>, Option<usize>) {
+    let mut lines = Vec::new();
+    let mut block_at = None;
+    let mut in_block = false;
+
+    for (index, line) in content.lines().enumerate() {
+        match line.trim() {
+            BLOCK_START => {
+                in_block = content.lines().skip(index + 1).any(|l| l.trim() == BLOCK_END);
+                block_at.get_or_insert(lines.len());
+            }
+            BLOCK_END if in_block => in_block = false,
+            _ if in_block => {}
+            trimmed if IGNORE_ENTRIES.contains(&trimmed) || LEGACY_ENTRIES.contains(&trimmed) => {
+                // Loose entry written before the block existed: migrate it
+                block_at.get_or_insert(lines.len());
+            }
+            _ => lines.push(line),
+        }
+    }
+    (lines, block_at)
+}
+
+/// Put hagi's block where it (or the first loose entry) was, or append it
+fn upsert_block(content: &str) -> String {
+    let (mut lines, block_at) = user_lines(content);
+    let mut block = vec![BLOCK_START];
+    block.extend_from_slice(IGNORE_ENTRIES);
+    block.push(BLOCK_END);
+
+    match block_at {
+        Some(at) => {
+            lines.splice(at..at, block);
+        }
+        None => {
+            while lines.last().is_some_and(|l| l.trim().is_empty()) {
+                lines.pop();
+            }
+            if !lines.is_empty() {
+                lines.push("");
+            }
+            lines.extend(block);
+        }
+    }
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    updated
+}
+
+/// Content without hagi's block and loose entries (trailing blank lines trimmed)
+fn remove_block(content: &str) -> String {
+    let (mut lines, _) = user_lines(content);
+    while lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    let mut remaining = lines.join("\n");
+    if !remaining.is_empty() {
+        remaining.push('\n');
+    }
+    remaining
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_block_migrates_loose_entries() {
+        let block = format!("{}\n{}\n{}\n", BLOCK_START, IGNORE_ENTRIES.join("\n"), BLOCK_END);
+
+        assert_eq!(upsert_block(""), block);
+        assert_eq!(upsert_block("target\n\n"), format!("target\n\n{}", block));
+        // Loose entries from older versions move into the block at the first one's position
+        assert_eq!(
+            upsert_block("target\n\n/.claude/\n/.serena/\nnode_modules\n/.mcp.json"),
+            format!("target\n\n{}node_modules\n", block)
+        );
+        let installed = upsert_block("target\n");
+        assert_eq!(upsert_block(&installed), installed);
+    }
+
+    #[test]
+    fn test_remove_block() {
+        let installed = upsert_block("target\n");
+        assert_eq!(remove_block(&installed), "target\n");
+        assert_eq!(remove_block("target\n/.mcp.json\n"), "target\n");
+        assert_eq!(remove_block(&upsert_block("")), "");
+    }
+
+    #[test]
+    fn test_unterminated_block_keeps_user_lines() {
+        let content = format!("target\n{}\n/.claude\n/.mcp.json\nnode_modules\n*.log\n", BLOCK_START);
+        assert_eq!(remove_block(&content), "target\nnode_modules\n*.log\n");
+
+        let block = format!("{}\n{}\n{}\n", BLOCK_START, IGNORE_ENTRIES.join("\n"), BLOCK_END);
+        assert_eq!(upsert_block(&content), format!("target\n{}node_modules\n*.log\n", block));
+    }
+}
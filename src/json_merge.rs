@@ -0,0 +1,522 @@
+use anyhow::bail;
+use colored::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// Keys whose local value wins unless `template-wins` is chosen (`hagi mcp enable/disable` toggles `disabled`)
+const LOCAL_KEYS: &[&str] = &["disabled"];
+
+/// Arrays merged as a union by default (permission rules)
+const UNION_PATHS: &[&str] = &["permissions.allow", "permissions.deny", "permissions.ask"];
+
+/// Parent of the hook lists merged as a union by default (`hooks.PreToolUse`, ...)
+const UNION_HOOKS: &str = "hooks";
+
+/// How conflicting values are resolved (`--merge-strategy`)
+///
+/// Without a strategy, local edits and `disabled` flags are kept, other values follow
+/// the template, and permission rules and hook lists are merged as a union.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Template values replace local ones, arrays included
+    TemplateWins,
+    /// Local values stay; only missing keys are added
+    KeepExisting,
+    /// Like the default, but every array is merged as a union
+    UnionArrays,
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeStrategy::TemplateWins => write!(f, "template-wins"),
+            MergeStrategy::KeepExisting => write!(f, "keep-existing"),
+            MergeStrategy::UnionArrays => write!(f, "union-arrays"),
+        }
+    }
+}
+
+impl FromStr for MergeStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "template-wins" => Ok(MergeStrategy::TemplateWins),
+            "keep-existing" => Ok(MergeStrategy::KeepExisting),
+            "union-arrays" => Ok(MergeStrategy::UnionArrays),
+            _ => bail!(
+                "Unknown merge strategy: '{}'\nValid strategies: template-wins, keep-existing, union-arrays",
+                s
+            ),
+        }
+    }
+}
+
+/// What a JSON merge did to one key (dotted path, e.g. `mcpServers.serena.disabled`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyChange {
+    /// New in the template
+    Added(String),
+    /// Template value replaced the local one
+    Updated(String),
+    /// Dropped from the template and unmodified locally
+    Removed(String),
+    /// Local value kept although the template differs
+    Kept(String),
+    /// Template array entries appended to the local array (union)
+    Extended(String, usize),
+    /// Same value locally and in the template
+    Unchanged(String),
+    /// Value hagi overwrote put back (`unmerge`)
+    Restored(String),
+    /// Entries hagi added taken out of an array (`unmerge`)
+    Pruned(String, usize),
+}
+
+/// What a merge put into an existing file, recorded in the install lock so that
+/// uninstall can take exactly that back out (`unmerge`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Contribution {
+    /// Key that did not exist before
+    Added { path: Vec<String>, value: Value },
+    /// Key whose `previous` value was overwritten
+    Replaced { path: Vec<String>, previous: Value, value: Value },
+    /// Entries appended to an existing array
+    Entries { path: Vec<String>, entries: Vec<Value> },
+}
+
+impl Contribution {
+    /// Key path from the document root
+    pub fn path(&self) -> &[String] {
+        match self {
+            Contribution::Added { path, .. }
+            | Contribution::Replaced { path, .. }
+            | Contribution::Entries { path, .. } => path,
+        }
+    }
+
+    /// Fold a later install's contribution to the same key into this one
+    fn absorb(self, later: Contribution) -> Contribution {
+        match (self, later) {
+            (Contribution::Added { .. }, Contribution::Replaced { path, value, .. }) => {
+                Contribution::Added { path, value }
+            }
+            (Contribution::Replaced { previous, .. }, Contribution::Replaced { path, value, .. }) => {
+                Contribution::Replaced { path, previous, value }
+            }
+            (Contribution::Entries { entries: ours, .. }, Contribution::Replaced { path, previous, value }) => {
+                // The array overwritten now held our entries; they were not there before hagi
+                let previous = match previous {
+                    Value::Array(items) => Value::Array(items.into_iter().filter(|i| !ours.contains(i)).collect()),
+                    other => other,
+                };
+                Contribution::Replaced { path, previous, value }
+            }
+            (Contribution::Entries { mut entries, .. }, Contribution::Entries { path, entries: more }) => {
+                entries.extend(more.into_iter().filter(|e| !entries.contains(e)).collect::<Vec<_>>());
+                Contribution::Entries { path, entries }
+            }
+            (Contribution::Added { value, .. }, Contribution::Entries { path, entries }) => {
+                Contribution::Added { path, value: with_entries(value, entries) }
+            }
+            (Contribution::Replaced { previous, value, .. }, Contribution::Entries { path, entries }) => {
+                Contribution::Replaced { path, previous, value: with_entries(value, entries) }
+            }
+            // Re-added after the key was deleted locally: nothing before it remains
+            (_, later) => later,
+        }
+    }
+}
+
+fn with_entries(value: Value, entries: Vec<Value>) -> Value {
+    match value {
+        Value::Array(mut items) => {
+            items.extend(entries);
+            Value::Array(items)
+        }
+        other => other,
+    }
+}
+
+/// Add a merge's contributions to those recorded by earlier installs
+pub fn accumulate(recorded: &mut Vec<Contribution>, contributions: Vec<Contribution>) {
+    for contribution in contributions {
+        match recorded.iter().position(|r| r.path() == contribution.path()) {
+            Some(i) => recorded[i] = recorded[i].clone().absorb(contribution),
+            None => recorded.push(contribution),
+        }
+    }
+}
+
+/// Take recorded contributions back out of a JSON document
+///
+/// Keys hagi added are removed and overwritten values restored only while they still
+/// hold what hagi wrote; anything edited since is kept. Array entries hagi added are
+/// removed wherever they still are.
+pub fn unmerge(local: &Value, contributions: &[Contribution]) -> (Value, Vec<KeyChange>) {
+    let mut doc = local.clone();
+    let mut changes = Vec::new();
+
+    // Latest first, so nested contributions are undone before the keys containing them
+    for contribution in contributions.iter().rev() {
+        let path = contribution.path();
+        let display = path.join(".");
+        let Some(current) = lookup_mut(&mut doc, path) else {
+            continue;
+        };
+
+        let remove_key = match contribution {
+            Contribution::Added { value, .. } if current == value => true,
+            Contribution::Added { value: Value::Array(ours), .. }
+            | Contribution::Entries { entries: ours, .. } => {
+                let Some(items) = current.as_array_mut() else {
+                    changes.push(KeyChange::Kept(display));
+                    continue;
+                };
+                let before = items.len();
+                items.retain(|item| !ours.contains(item));
+                let removed = before - items.len();
+                if items.is_empty() && matches!(contribution, Contribution::Added { .. }) {
+                    true
+                } else {
+                    if removed > 0 {
+                        changes.push(KeyChange::Pruned(display, removed));
+                    }
+                    false
+                }
+            }
+            Contribution::Replaced { previous, value, .. } if current == value => {
+                *current = previous.clone();
+                changes.push(KeyChange::Restored(display));
+                false
+            }
+            Contribution::Replaced { previous: Value::Array(previous), value: Value::Array(ours), .. }
+                if current.is_array() =>
+            {
+                // Array edited after hagi overwrote it: restore it, keeping the entries added since
+                let mut items = previous.clone();
+                for item in current.as_array().into_iter().flatten() {
+                    if !ours.contains(item) && !items.contains(item) {
+                        items.push(item.clone());
+                    }
+                }
+                *current = Value::Array(items);
+                changes.push(KeyChange::Restored(display));
+                false
+            }
+            Contribution::Added { .. } | Contribution::Replaced { .. } => {
+                changes.push(KeyChange::Kept(display));
+                false
+            }
+        };
+
+        if remove_key
+            && let Some((key, parent)) = path.split_last()
+            && let Some(parent) = lookup_mut(&mut doc, parent).and_then(Value::as_object_mut)
+        {
+            parent.remove(key);
+            changes.push(KeyChange::Removed(path.join(".")));
+        }
+    }
+    (doc, changes)
+}
+
+fn lookup_mut<'v>(doc: &'v mut Value, path: &[String]) -> Option<&'v mut Value> {
+    path.iter().try_fold(doc, |value, key| value.get_mut(key.as_str()))
+}
+
+/// Merge a template into an existing JSON config
+///
+/// Keys only present locally (custom servers, extra permissions) are always preserved.
+/// With `base` (the template as last installed), only keys the template changed since
+/// then are updated and local edits to other keys win; without it the template wins,
+/// except for `disabled` flags. `strategy` overrides how conflicts and arrays are handled.
+pub fn merge(
+    local: &Value,
+    template: &Value,
+    base: Option<&Value>,
+    strategy: Option<MergeStrategy>,
+) -> (Value, Vec<KeyChange>) {
+    let (merged, changes, _) = merge_recorded(local, template, base, strategy);
+    (merged, changes)
+}
+
+/// `merge`, also returning what the template contributed (for `unmerge`)
+pub fn merge_recorded(
+    local: &Value,
+    template: &Value,
+    base: Option<&Value>,
+    strategy: Option<MergeStrategy>,
+) -> (Value, Vec<KeyChange>, Vec<Contribution>) {
+    let mut merged = local.clone();
+    let mut ctx = MergeContext {
+        strategy,
+        keys: Vec::new(),
+        changes: Vec::new(),
+        contributions: Vec::new(),
+    };
+    if let (Some(merged_obj), Some(template_obj)) = (merged.as_object_mut(), template.as_object()) {
+        ctx.merge_objects(merged_obj, template_obj, base.and_then(Value::as_object));
+    }
+    (merged, ctx.changes, ctx.contributions)
+}
+
+struct MergeContext {
+    strategy: Option<MergeStrategy>,
+    /// Path of the object being merged
+    keys: Vec<String>,
+    changes: Vec<KeyChange>,
+    contributions: Vec<Contribution>,
+}
+
+impl MergeContext {
+    fn merge_objects(
+        &mut self,
+        local: &mut Map<String, Value>,
+        template: &Map<String, Value>,
+        base: Option<&Map<String, Value>>,
+    ) {
+        for (key, template_value) in template {
+            self.keys.push(key.clone());
+            self.merge_key(local, key, template_value, base.and_then(|b| b.get(key)));
+            self.keys.pop();
+        }
+
+        // Keys the template dropped since install, unless edited locally
+        if let Some(base) = base
+            && self.strategy != Some(MergeStrategy::KeepExisting)
+        {
+            for (key, base_value) in base {
+                if !template.contains_key(key) && local.get(key) == Some(base_value) {
+                    local.remove(key);
+                    self.changes.push(KeyChange::Removed(self.child_path(key)));
+                }
+            }
+        }
+    }
+
+    fn merge_key(
+        &mut self,
+        local: &mut Map<String, Value>,
+        key: &str,
+        template_value: &Value,
+        base_value: Option<&Value>,
+    ) {
+        let path = self.keys.join(".");
+
+        let Some(local_value) = local.get_mut(key) else {
+            if base_value.is_some() && self.strategy != Some(MergeStrategy::TemplateWins) {
+                // Deleted locally after install
+                self.changes.push(KeyChange::Kept(path));
+            } else {
+                local.insert(key.to_string(), template_value.clone());
+                self.changes.push(KeyChange::Added(path));
+                self.contributions.push(Contribution::Added {
+                    path: self.keys.clone(),
+                    value: template_value.clone(),
+                });
+            }
+            return;
+        };
+
+        if let (Some(local_obj), Some(template_obj)) = (local_value.as_object_mut(), template_value.as_object()) {
+            self.merge_objects(local_obj, template_obj, base_value.and_then(Value::as_object));
+            return;
+        }
+        if local_value == template_value {
+            self.changes.push(KeyChange::Unchanged(path));
+            return;
+        }
+        if let (Some(local_items), Some(template_items)) = (local_value.as_array_mut(), template_value.as_array())
+            && self.strategy != Some(MergeStrategy::TemplateWins)
+        {
+            let missing: Vec<Value> = template_items
+                .iter()
+                .filter(|item| !local_items.contains(item))
+                .cloned()
+                .collect();
+            if missing.is_empty() {
+                // Local entries on top of every template entry
+                self.changes.push(KeyChange::Unchanged(path));
+                return;
+            }
+            if self.unions(&path) {
+                self.changes.push(KeyChange::Extended(path, missing.len()));
+                local_items.extend(missing.iter().cloned());
+                self.contributions.push(Contribution::Entries { path: self.keys.clone(), entries: missing });
+                return;
+            }
+        }
+
+        let keep_local = match self.strategy {
+            Some(MergeStrategy::TemplateWins) => false,
+            Some(MergeStrategy::KeepExisting) => true,
+            None | Some(MergeStrategy::UnionArrays) => {
+                base_value.is_some_and(|b| b == template_value) || LOCAL_KEYS.contains(&key)
+            }
+        };
+        if keep_local {
+            self.changes.push(KeyChange::Kept(path));
+        } else {
+            let previous = std::mem::replace(local_value, template_value.clone());
+            self.changes.push(KeyChange::Updated(path));
+            self.contributions.push(Contribution::Replaced {
+                path: self.keys.clone(),
+                previous,
+                value: template_value.clone(),
+            });
+        }
+    }
+
+    fn child_path(&self, key: &str) -> String {
+        self.keys.iter().map(String::as_str).chain([key]).collect::<Vec<_>>().join(".")
+    }
+
+    /// Whether the array at `path` is merged as a union
+    fn unions(&self, path: &str) -> bool {
+        match self.strategy {
+            Some(MergeStrategy::UnionArrays) => true,
+            Some(MergeStrategy::TemplateWins | MergeStrategy::KeepExisting) => false,
+            None => {
+                UNION_PATHS.contains(&path)
+                    || path
+                        .strip_prefix(UNION_HOOKS)
+                        .and_then(|rest| rest.strip_prefix('.'))
+                        .is_some_and(|event| !event.contains('.'))
+            }
+        }
+    }
+}
+
+/// Print the per-key report of a merge (identical keys are only counted)
+pub fn print_changes(changes: &[KeyChange]) {
+    let mut unchanged = 0;
+    for change in changes {
+        match change {
+            KeyChange::Added(path) => println!("    {} {}", "+".green(), path),
+            KeyChange::Updated(path) => println!("    {} {} {}", "~".yellow(), path, "(template value)".dimmed()),
+            KeyChange::Removed(path) => println!("    {} {}", "-".red(), path),
+            KeyChange::Kept(path) => println!("    {} {} {}", "=".blue(), path, "(local value kept)".dimmed()),
+            KeyChange::Extended(path, count) => {
+                println!("    {} {} {}", "+".green(), path, format!("({} entries added)", count).dimmed())
+            }
+            KeyChange::Restored(path) => println!("    {} {} {}", "~".yellow(), path, "(previous value restored)".dimmed()),
+            KeyChange::Pruned(path, count) => {
+                println!("    {} {} {}", "-".red(), path, format!("({} entries removed)", count).dimmed())
+            }
+            KeyChange::Unchanged(_) => unchanged += 1,
+        }
+    }
+    if unchanged > 0 {
+        println!("    {}", format!("{} key(s) unchanged", unchanged).dimmed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_merge_keeps_user_keys_and_disabled_flags() {
+        let local = json!({"mcpServers": {
+            "serena": {"command": "uvx", "disabled": false},
+            "mine": {"command": "my-server"}
+        }});
+        let template = json!({"mcpServers": {
+            "serena": {"command": "uvx2", "disabled": true},
+            "context7": {"command": "npx"}
+        }});
+        let (merged, changes) = merge(&local, &template, None, None);
+        assert_eq!(
+            merged,
+            json!({"mcpServers": {
+                "serena": {"command": "uvx2", "disabled": false},
+                "mine": {"command": "my-server"},
+                "context7": {"command": "npx"}
+            }})
+        );
+        assert_eq!(
+            changes,
+            vec![
+                KeyChange::Added("mcpServers.context7".into()),
+                KeyChange::Updated("mcpServers.serena.command".into()),
+                KeyChange::Kept("mcpServers.serena.disabled".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_with_base_applies_only_template_changes() {
+        let base = json!({"a": 1, "b": 1, "old": true});
+        let local = json!({"a": 2, "b": 1, "old": true});
+        let template = json!({"a": 1, "b": 3});
+        let (merged, changes) = merge(&local, &template, Some(&base), None);
+        assert_eq!(merged, json!({"a": 2, "b": 3}));
+        assert_eq!(
+            changes,
+            vec![
+                KeyChange::Kept("a".into()),
+                KeyChange::Updated("b".into()),
+                KeyChange::Removed("old".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let local = json!({"a": 1, "permissions": {"allow": ["x"]}, "list": [1]});
+        let template = json!({"a": 2, "b": 3, "permissions": {"allow": ["y"]}, "list": [2]});
+
+        // Default: permission rules are unioned, other arrays and values follow the template
+        let (merged, _) = merge(&local, &template, None, None);
+        assert_eq!(merged, json!({"a": 2, "b": 3, "permissions": {"allow": ["x", "y"]}, "list": [2]}));
+
+        let (merged, _) = merge(&local, &template, None, Some(MergeStrategy::TemplateWins));
+        assert_eq!(merged, json!({"a": 2, "b": 3, "permissions": {"allow": ["y"]}, "list": [2]}));
+
+        let (merged, changes) = merge(&local, &template, None, Some(MergeStrategy::KeepExisting));
+        assert_eq!(merged, json!({"a": 1, "b": 3, "permissions": {"allow": ["x"]}, "list": [1]}));
+        assert!(changes.contains(&KeyChange::Kept("a".into())));
+
+        let (merged, _) = merge(&local, &template, None, Some(MergeStrategy::UnionArrays));
+        assert_eq!(merged, json!({"a": 2, "b": 3, "permissions": {"allow": ["x", "y"]}, "list": [1, 2]}));
+    }
+
+    #[test]
+    fn test_merge_template_wins_recursively() {
+        let local = json!({"a": 1, "b": {"c": 2, "d": 3}});
+        let template = json!({"b": {"c": 20, "e": 4}, "f": 5});
+        let (merged, _) = merge(&local, &template, None, Some(MergeStrategy::TemplateWins));
+        assert_eq!(merged, json!({"a": 1, "b": {"c": 20, "d": 3, "e": 4}, "f": 5}));
+    }
+
+    #[test]
+    fn test_unmerge_restores_prior_settings() {
+        let before = json!({"model": "opus", "permissions": {"allow": ["mine"], "defaultMode": "plan"}});
+        let template = json!({"model": "sonnet", "permissions": {"allow": ["x"]}, "hooks": {"Stop": []}});
+
+        let mut recorded = Vec::new();
+        let (installed, _, contributions) = merge_recorded(&before, &template, None, Some(MergeStrategy::TemplateWins));
+        accumulate(&mut recorded, contributions);
+        // A reinstall contributes nothing new
+        let (installed, _, contributions) = merge_recorded(&installed, &template, None, None);
+        accumulate(&mut recorded, contributions);
+
+        let (restored, _) = unmerge(&installed, &recorded);
+        assert_eq!(restored, before);
+
+        // Edits made after install are kept
+        let mut edited = installed.clone();
+        edited["permissions"]["allow"].as_array_mut().unwrap().push(json!("later"));
+        edited["model"] = json!("haiku");
+        let (restored, _) = unmerge(&edited, &recorded);
+        assert_eq!(
+            restored,
+            json!({"model": "haiku", "permissions": {"allow": ["mine", "later"], "defaultMode": "plan"}})
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use regex::Regex;
+use similar::{ChangeTag, TextDiff};
+use std::sync::LazyLock;
+
+use crate::merge::{self, MergeResult};
+
+/// Start or end marker of a preserved block: `<!-- hagi:keep:NAME:start -->`
+///
+/// The older `<!-- hagi:project:start -->` form is read as the block `project`.
+static MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!-- hagi:(?:keep:)?([\w.-]+):(start|end) -->").expect("valid regex"));
+
+/// Heading of the trailing section holding blocks the template no longer has
+const ORPHANED_HEADER: &str = "<!-- hagi:orphaned -->\n## Orphaned sections\n\n\
+The template no longer has these preserved sections; move their content elsewhere or delete them.\n";
+
+/// Block created by `adopt` when the template has none
+const ADOPTED_BLOCK: &str = "custom";
+
+/// A preserved block in a text file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Block<'a> {
+    name: &'a str,
+    /// Byte range of the content between the markers
+    body_start: usize,
+    body_end: usize,
+    body: &'a str,
+}
+
+/// Result of carrying preserved blocks into a new template
+#[derive(Debug, PartialEq, Eq)]
+pub struct Applied {
+    pub text: String,
+    /// Blocks whose local content replaced the template's
+    pub carried: Vec<String>,
+    /// Blocks the template no longer has (moved to the orphaned section)
+    pub orphaned: Vec<String>,
+}
+
+/// Whether `content` has any preserved block
+pub fn has_blocks(content: &str) -> bool {
+    !blocks(content).is_empty()
+}
+
+/// Preserved blocks of `content` in order (unterminated blocks are ignored)
+fn blocks(content: &str) -> Vec<Block<'_>> {
+    let mut found = Vec::new();
+    let mut open: Option<(&str, usize)> = None;
+
+    for caps in MARKER.captures_iter(content) {
+        let marker = caps.get(0).expect("whole match");
+        let name = caps.get(1).expect("name group").as_str();
+        match (&caps[2], open) {
+            ("start", _) => open = Some((name, marker.end())),
+            ("end", Some((open_name, body_start))) if open_name == name => {
+                found.push(Block {
+                    name,
+                    body_start,
+                    body_end: marker.start(),
+                    body: &content[body_start..marker.start()],
+                });
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Render `template` with the blocks of `existing` carried over by name
+///
+/// Everything outside the blocks comes from the template. Blocks of `existing` that
+/// the template does not have are appended in a trailing "orphaned" section, so no
+/// preserved content is ever dropped.
+pub fn apply(template: &str, existing: &str) -> Applied {
+    let mut applied = carry(template, existing);
+    let template_names: Vec<&str> = blocks(template).iter().map(|b| b.name).collect();
+    let missing: Vec<Block> = blocks(existing)
+        .into_iter()
+        .filter(|b| !template_names.contains(&b.name))
+        .collect();
+    applied.orphaned = append_orphaned(&mut applied.text, &missing);
+    applied
+}
+
+/// `template` with the bodies of its blocks taken from `existing` (nothing orphaned)
+fn carry(template: &str, existing: &str) -> Applied {
+    let local = blocks(existing);
+    let mut text = String::with_capacity(template.len());
+    let mut carried = Vec::new();
+    let mut last = 0;
+    for block in &blocks(template) {
+        text.push_str(&template[last..block.body_start]);
+        match local.iter().find(|b| b.name == block.name) {
+            Some(kept) => {
+                text.push_str(kept.body);
+                if kept.body != block.body && !carried.iter().any(|c| c == block.name) {
+                    carried.push(block.name.to_string());
+                }
+            }
+            None => text.push_str(block.body),
+        }
+        last = block.body_end;
+    }
+    text.push_str(&template[last..]);
+    Applied { text, carried, orphaned: Vec::new() }
+}
+
+/// Append `missing` blocks to the orphaned section of `text` (created when absent)
+///
+/// Returns the names of the appended blocks.
+fn append_orphaned(text: &mut String, missing: &[Block]) -> Vec<String> {
+    let mut orphaned: Vec<String> = Vec::new();
+    for block in missing {
+        if orphaned.iter().any(|o| o == block.name) {
+            continue;
+        }
+        if orphaned.is_empty() && !text.contains(ORPHANED_HEADER) {
+            let trimmed = text.trim_end().len();
+            text.truncate(trimmed);
+            text.push_str("\n\n");
+            text.push_str(ORPHANED_HEADER);
+        }
+        text.push_str(&format!(
+            "\n<!-- hagi:keep:{name}:start -->{}<!-- hagi:keep:{name}:end -->\n",
+            block.body,
+            name = block.name
+        ));
+        orphaned.push(block.name.to_string());
+    }
+    orphaned
+}
+
+/// Render `template` with the custom content of an unmarked `existing` file inside a block
+///
+/// Lines of `existing` that the template does not have go into the template's first
+/// block (or a new `custom` block at the end), so the next update keeps them.
+/// Returns the text and the name of the block.
+pub fn adopt(template: &str, existing: &str) -> (String, String) {
+    let diff = TextDiff::from_lines(template, existing);
+    let custom: String = diff
+        .iter_all_changes()
+        .filter(|change| change.tag() == ChangeTag::Insert)
+        .map(|change| change.value())
+        .collect();
+    let custom = custom.trim_matches('\n');
+
+    let template_blocks = blocks(template);
+    let Some(block) = template_blocks.first() else {
+        let mut text = template.trim_end().to_string();
+        text.push_str(&format!(
+            "\n\n<!-- hagi:keep:{name}:start -->\n{}\n<!-- hagi:keep:{name}:end -->\n",
+            custom,
+            name = ADOPTED_BLOCK
+        ));
+        return (text, ADOPTED_BLOCK.to_string());
+    };
+
+    let text = format!(
+        "{}\n{}\n{}",
+        &template[..block.body_start],
+        custom,
+        &template[block.body_end..]
+    );
+    (text, block.name.to_string())
+}
+
+/// Three-way merge of a file with preserved blocks
+///
+/// The blocks of `local` are carried into `base` and `template` first, so the merge
+/// never conflicts inside a block and still keeps local edits outside the blocks.
+/// Blocks the previous template had and the new one dropped move to the orphaned
+/// section after the merge; blocks added locally stay where they are. Also returns
+/// the moved blocks.
+pub fn merge3(base: &str, local: &str, template: &str) -> (MergeResult, Vec<String>) {
+    let base_names: Vec<&str> = blocks(base).iter().map(|b| b.name).collect();
+    let template_names: Vec<&str> = blocks(template).iter().map(|b| b.name).collect();
+    let mut result = merge::merge3(&carry(base, local).text, local, &carry(template, local).text);
+
+    // Blocks the merge removed along with the template's copy
+    let merged_names: Vec<String> = blocks(&result.text).iter().map(|b| b.name.to_string()).collect();
+    let dropped: Vec<Block> = blocks(local)
+        .into_iter()
+        .filter(|b| base_names.contains(&b.name) && !template_names.contains(&b.name))
+        .filter(|b| !merged_names.iter().any(|m| m == b.name))
+        .collect();
+    let orphaned = append_orphaned(&mut result.text, &dropped);
+    (result, orphaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = "# Title\n\n<!-- hagi:keep:notes:start -->\n(notes)\n<!-- hagi:keep:notes:end -->\n\n\
+<!-- hagi:keep:todo:start -->\n(todo)\n<!-- hagi:keep:todo:end -->\n\n## Rules\n";
+
+    #[test]
+    fn test_apply_carries_blocks_by_name() {
+        let existing = "# Old title\n\n<!-- hagi:keep:todo:start -->\n- ship it\n<!-- hagi:keep:todo:end -->\n\n\
+<!-- hagi:keep:notes:start -->\nmine\n<!-- hagi:keep:notes:end -->\n";
+        let applied = apply(TEMPLATE, existing);
+        assert_eq!(
+            applied.text,
+            TEMPLATE.replace("(notes)", "mine").replace("(todo)", "- ship it")
+        );
+        assert_eq!(applied.carried, vec!["notes", "todo"]);
+        assert!(applied.orphaned.is_empty());
+
+        // The older hagi:project form is the block "project"
+        let legacy = "<!-- hagi:project:start -->\nabout\n<!-- hagi:project:end -->\n";
+        let template = "A\n<!-- hagi:keep:project:start -->\n<!-- hagi:keep:project:end -->\n";
+        assert_eq!(
+            apply(template, legacy).text,
+            "A\n<!-- hagi:keep:project:start -->\nabout\n<!-- hagi:keep:project:end -->\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_moves_removed_blocks_to_orphaned_section() {
+        let existing = "<!-- hagi:keep:notes:start -->\nmine\n<!-- hagi:keep:notes:end -->\n\
+<!-- hagi:keep:old:start -->\nkeep me\n<!-- hagi:keep:old:end -->\n";
+        let applied = apply(TEMPLATE, existing);
+        assert_eq!(applied.orphaned, vec!["old"]);
+        assert!(applied.text.ends_with(
+            "## Rules\n\n<!-- hagi:orphaned -->\n## Orphaned sections\n\n\
+The template no longer has these preserved sections; move their content elsewhere or delete them.\n\n\
+<!-- hagi:keep:old:start -->\nkeep me\n<!-- hagi:keep:old:end -->\n"
+        ));
+        // Applying again does not duplicate the orphaned section
+        assert_eq!(apply(TEMPLATE, &applied.text).text, applied.text);
+    }
+
+    #[test]
+    fn test_merge3_keeps_edits_outside_blocks() {
+        let local = TEMPLATE.replace("(notes)", "mine").replace("## Rules\n", "## Rules\n- my rule\n");
+        let template = TEMPLATE.replace("# Title", "# New title").replace("(notes)", "(write notes)");
+        let (result, orphaned) = merge3(TEMPLATE, &local, &template);
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.text, local.replace("# Title", "# New title"));
+        assert!(orphaned.is_empty());
+
+        // A block the new template dropped moves to the orphaned section, a block
+        // added locally stays in place
+        let local = format!("{}\n<!-- hagi:keep:mine:start -->\nx\n<!-- hagi:keep:mine:end -->\n", local);
+        let template = template.replace("<!-- hagi:keep:todo:start -->\n(todo)\n<!-- hagi:keep:todo:end -->\n\n", "");
+        let (result, orphaned) = merge3(TEMPLATE, &local, &template);
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(orphaned, vec!["todo"]);
+        assert!(result.text.contains("- my rule\n\n<!-- hagi:keep:mine:start -->"));
+        assert!(result.text.ends_with("<!-- hagi:keep:todo:start -->\n(todo)\n<!-- hagi:keep:todo:end -->\n"));
+    }
+
+    #[test]
+    fn test_adopt_wraps_custom_lines() {
+        let existing = "# Title\n\nMy own rule\n\n## Rules\nAnother\n";
+        let (text, block) = adopt(TEMPLATE, existing);
+        assert_eq!(block, "notes");
+        assert!(text.contains("<!-- hagi:keep:notes:start -->\nMy own rule\nAnother\n<!-- hagi:keep:notes:end -->"));
+        assert_eq!(apply(TEMPLATE, &text).text, text);
+    }
+}
@@ -0,0 +1,252 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::ignore::IgnoreMode;
+use crate::json_merge::{self, Contribution};
+use crate::templates::Category;
+
+/// Install lock filename (stored in the install target directory, e.g. `.claude/`)
+pub const LOCK_FILE: &str = ".hagi-lock.json";
+
+/// Directory (inside the install target) holding pristine copies of installed templates
+///
+/// Used as the merge base when a locally modified file meets an updated template.
+pub const BASE_DIR: &str = ".hagi-base";
+
+/// Current lock file format version
+const LOCK_FORMAT_VERSION: u32 = 1;
+
+/// Record of every file hagi wrote into an install target
+///
+/// # Format
+/// ```json
+/// {
+///   "version": 1,
+///   "files": {
+///     "instructions/git-workflow.md": {
+///       "category": "instructions",
+///       "hash": "9f86d08...",
+///       "hagi_version": "0.3.0",
+///       "installed_at": "2025-01-01T12:00:00+09:00"
+///     }
+///   }
+/// }
+/// ```
+///
+/// Paths are relative to the install target and always use `/` separators.
+/// Hashes are taken after template variables are rendered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstallLock {
+    pub version: u32,
+    /// Template variables supplied with `--var` (reused by later installs and `hagi diff`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, String>,
+    /// Profiles selected with `--profile` (empty = detect from project files)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub profiles: Vec<String>,
+    /// Template packs used by the last install, in precedence order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packs: Vec<PackPin>,
+    /// Where the last install wrote the ignore entries (`--ignore-mode`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_mode: Option<IgnoreMode>,
+    #[serde(default)]
+    pub files: BTreeMap<String, LockEntry>,
+    /// Keys and array entries merged into files that existed before (global `settings.json`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub contributions: BTreeMap<String, Vec<Contribution>>,
+}
+
+/// A template pack recorded in the lock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackPin {
+    /// Pack source as given (`<dir|git-url>[#ref]`)
+    pub source: String,
+    /// Commit the pack was installed from (git packs only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Listed in the user config rather than given with `--templates`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub configured: bool,
+}
+
+/// A single file written by hagi
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockEntry {
+    /// Template category (None for paths outside the category system)
+    pub category: Option<Category>,
+    /// SHA-256 of the content hagi wrote
+    pub hash: String,
+    /// SHA-256 of the template the content was based on, when it differs from `hash`
+    /// (e.g. after a three-way merge kept local changes)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_hash: Option<String>,
+    /// hagi version that wrote the file
+    pub hagi_version: String,
+    /// RFC 3339 timestamp of the write
+    pub installed_at: String,
+}
+
+impl Default for InstallLock {
+    fn default() -> Self {
+        Self {
+            version: LOCK_FORMAT_VERSION,
+            vars: BTreeMap::new(),
+            profiles: Vec::new(),
+            packs: Vec::new(),
+            ignore_mode: None,
+            files: BTreeMap::new(),
+            contributions: BTreeMap::new(),
+        }
+    }
+}
+
+impl InstallLock {
+    /// Load the lock from `target_dir`, or an empty lock if none exists yet
+    pub fn load(target_dir: &Path) -> Result<Self> {
+        let lock_path = target_dir.join(LOCK_FILE);
+        if !lock_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&lock_path)
+            .with_context(|| format!("Failed to read install lock: {}", lock_path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse install lock: {}", lock_path.display()))
+    }
+
+    /// Write the lock to `target_dir`
+    pub fn save(&self, target_dir: &Path) -> Result<()> {
+        let lock_path = target_dir.join(LOCK_FILE);
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize install lock")?;
+        fs::write(&lock_path, content)
+            .with_context(|| format!("Failed to write install lock: {}", lock_path.display()))
+    }
+
+    /// Record a file written by hagi (replaces any previous entry for the path)
+    pub fn record(&mut self, relative_path: &Path, category: Option<Category>, content: &[u8]) {
+        self.record_with_base(relative_path, category, content, content);
+    }
+
+    /// Record a file whose written content differs from the template it was based on
+    pub fn record_with_base(
+        &mut self,
+        relative_path: &Path,
+        category: Option<Category>,
+        content: &[u8],
+        template: &[u8],
+    ) {
+        let hash = content_hash(content);
+        let template_hash = content_hash(template);
+        self.files.insert(
+            lock_key(relative_path),
+            LockEntry {
+                category,
+                base_hash: (template_hash != hash).then_some(template_hash),
+                hash,
+                hagi_version: env!("CARGO_PKG_VERSION").to_string(),
+                installed_at: chrono::Local::now().to_rfc3339(),
+            },
+        );
+    }
+
+    /// Add what a JSON merge put into an existing file to the earlier installs' record
+    pub fn record_contributions(&mut self, relative_path: &Path, contributions: Vec<Contribution>) {
+        let recorded = self.contributions.entry(lock_key(relative_path)).or_default();
+        json_merge::accumulate(recorded, contributions);
+    }
+
+    /// Contributions recorded for a merged file (None if installed before they were recorded)
+    pub fn contributions(&self, relative_path: &Path) -> Option<&[Contribution]> {
+        self.contributions.get(&lock_key(relative_path)).map(Vec::as_slice)
+    }
+
+    /// Forget a file and its contributions
+    pub fn remove(&mut self, relative_path: &Path) {
+        let key = lock_key(relative_path);
+        self.files.remove(&key);
+        self.contributions.remove(&key);
+    }
+
+    /// Look up the entry for a relative path
+    pub fn get(&self, relative_path: &Path) -> Option<&LockEntry> {
+        self.files.get(&lock_key(relative_path))
+    }
+
+    /// Number of recorded files per category name (uncategorized files are counted as "other")
+    pub fn count_by_category(&self) -> BTreeMap<&'static str, usize> {
+        let mut counts = BTreeMap::new();
+        for entry in self.files.values() {
+            let name = entry.category.map(|c| c.as_str()).unwrap_or("other");
+            *counts.entry(name).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+impl LockEntry {
+    /// Hash of the template this file was installed from (the three-way merge base)
+    pub fn base_hash(&self) -> &str {
+        self.base_hash.as_deref().unwrap_or(&self.hash)
+    }
+}
+
+/// Store a pristine copy of an installed template under `BASE_DIR`
+pub fn store_base(target_dir: &Path, relative_path: &Path, template: &str) -> Result<()> {
+    let base_path = target_dir.join(BASE_DIR).join(relative_path);
+    if let Some(parent) = base_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::write(&base_path, template)
+        .with_context(|| format!("Failed to write merge base: {}", base_path.display()))
+}
+
+/// Load the pristine copy of a template if it matches the expected hash
+pub fn load_base(target_dir: &Path, relative_path: &Path, expected_hash: &str) -> Option<String> {
+    let base_path = target_dir.join(BASE_DIR).join(relative_path);
+    fs::read_to_string(base_path)
+        .ok()
+        .filter(|content| content_hash(content.as_bytes()) == expected_hash)
+}
+
+/// Normalize a relative path into a lock key (`/`-separated)
+fn lock_key(relative_path: &Path) -> String {
+    relative_path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// SHA-256 of content as lowercase hex
+pub fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_get() {
+        let mut lock = InstallLock::default();
+        lock.record(Path::new("instructions/a.md"), Some(Category::Instructions), b"hello");
+
+        let entry = &lock.files["instructions/a.md"];
+        assert_eq!(entry.category, Some(Category::Instructions));
+        assert_eq!(
+            entry.hash,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(lock.count_by_category().get("instructions"), Some(&1));
+    }
+}
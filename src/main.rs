@@ -2,8 +2,22 @@ use anyhow::{bail, Result};
 use clap::{Parser, Subcommand};
 
 mod commands;
+mod githooks;
+mod guard;
+mod history;
+mod ignore;
+mod json_merge;
+mod keep;
+mod lock;
+mod merge;
+mod packs;
+mod profiles;
+mod project_config;
+mod render;
+mod rules;
 mod templates;
 mod utils;
+mod worktree;
 
 #[derive(Parser)]
 #[command(name = "hagi")]
@@ -34,9 +48,50 @@ enum Commands {
         #[arg(long = "skip", value_name = "PATH", num_args = 1..)]
         skip: Vec<String>,
 
-        /// Install only specific categories (instructions, skills, hooks, config, docs, designs)
+        /// Install only specific categories (instructions, skills, hooks, config, docs, designs, agents)
         #[arg(long = "only", value_name = "CATEGORY", num_args = 1..)]
         only: Vec<String>,
+
+        /// Set a template variable (e.g. --var project_name=myapp); repeatable
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+
+        /// Use these project profiles instead of detecting them (rust, shell, node, python, go)
+        #[arg(long = "profile", value_name = "NAME", num_args = 1..)]
+        profiles: Vec<String>,
+
+        /// Layer a template pack over the embedded templates: <DIR|GIT-URL>[#REF]; repeatable
+        #[arg(long = "templates", value_name = "SOURCE")]
+        packs: Vec<String>,
+
+        /// Update git template packs to the latest commit instead of the pinned one
+        #[arg(long)]
+        update_packs: bool,
+
+        /// Also install the pre-push hook (scans pushed commits for .claude/ files and signatures)
+        #[arg(long)]
+        pre_push: bool,
+
+        /// Signature handling in commit messages: reject (default) or strip (also installs prepare-commit-msg)
+        #[arg(long, value_name = "MODE")]
+        message_mode: Option<String>,
+
+        /// Where to write the ignore entries: gitignore (default) or exclude (.git/info/exclude, not committed)
+        #[arg(long, value_name = "MODE")]
+        ignore_mode: Option<String>,
+
+        /// In a linked git worktree, symlink .claude to the main worktree's .claude/ instead of copying
+        #[arg(long)]
+        shared: bool,
+
+        /// How existing JSON configs are merged: template-wins, keep-existing or union-arrays
+        /// (default: union permission rules and hook lists, keep local edits)
+        #[arg(long, value_name = "STRATEGY")]
+        merge_strategy: Option<String>,
+
+        /// Wrap the custom content of a CLAUDE.md without hagi:keep blocks into a preserved block
+        #[arg(long)]
+        adopt: bool,
     },
 
     /// Uninstall hagi configuration
@@ -48,11 +103,38 @@ enum Commands {
         /// Skip confirmation prompt
         #[arg(short = 'y', long)]
         yes: bool,
+
+        /// Show what would be removed without removing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Keep files of these categories (instructions, skills, hooks, config, docs, designs, agents)
+        #[arg(long = "keep", value_name = "CATEGORY", num_args = 1..)]
+        keep: Vec<String>,
+
+        /// Remove the whole .claude/ directory, plus the symlink and git hooks even if modified
+        #[arg(long, conflicts_with = "keep")]
+        purge: bool,
     },
 
     /// Show installation status
     Status,
 
+    /// Show differences between embedded templates and installed .claude/ files
+    Diff {
+        /// Compare only specific categories (instructions, skills, hooks, config, docs, designs)
+        #[arg(long = "category", value_name = "CATEGORY", num_args = 1..)]
+        category: Vec<String>,
+
+        /// Compare only these template paths (e.g. instructions/git-workflow.md, skills)
+        #[arg(value_name = "PATH")]
+        paths: Vec<String>,
+
+        /// Also list identical files in the summary (counted only by default)
+        #[arg(long)]
+        identical: bool,
+    },
+
     /// Update hagi templates and configuration
     Update,
 
@@ -73,6 +155,30 @@ enum Commands {
         #[command(subcommand)]
         command: SyncCommands,
     },
+
+    /// Git hook commands (called by the installed hooks)
+    Hook {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
+
+    /// Git helpers for the project repository
+    Git {
+        #[command(subcommand)]
+        command: GitCommands,
+    },
+
+    /// Audit the repository history
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommands,
+    },
+
+    /// Claude Code hook handlers (called by Claude Code)
+    Guard {
+        #[command(subcommand)]
+        command: GuardCommands,
+    },
 }
 
 #[derive(Subcommand)]
@@ -141,37 +247,169 @@ enum SyncCommands {
     Status,
 }
 
+#[derive(Subcommand)]
+enum GitCommands {
+    /// Remove tracked .claude/ and .mcp.json from the index (kept on disk) and commit
+    Untrack {
+        /// Skip confirmation prompt
+        #[arg(short = 'y', long)]
+        yes: bool,
+
+        /// Show what would be done without changing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Branch to create when the current branch is protected (default: chore/untrack-claude)
+        #[arg(long)]
+        branch: Option<String>,
+
+        /// Commit message
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditCommands {
+    /// Scan commit history for .claude/ files and forbidden patterns (exits 1 if found)
+    Commits {
+        /// Revision range for git log (default: HEAD), e.g. origin/main..HEAD or --all
+        #[arg(allow_hyphen_values = true)]
+        range: Option<String>,
+        /// Show the git filter-repo / git rm --cached commands to clean up (not run)
+        #[arg(long)]
+        fix: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCommands {
+    /// Run the checks for a git hook (pre-commit, commit-msg, pre-push, prepare-commit-msg)
+    Run {
+        /// Hook name
+        name: String,
+        /// Arguments git passed to the hook
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum GuardCommands {
+    /// PreToolUse hook: apply guard rules to a tool call (hook JSON on stdin)
+    Pretooluse,
+    /// Show which guard rule fires for a hook payload
+    Test {
+        /// PreToolUse hook JSON (e.g. '{"tool_name":"Bash","tool_input":{"command":"git add -A"}}'), or - for stdin
+        payload: String,
+    },
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Install { global, chat, dry_run, skip, only } => {
-            if !only.is_empty() && (global || chat) {
-                bail!("--only cannot be used with --global or --chat");
+        Commands::Install {
+            global,
+            chat,
+            dry_run,
+            skip,
+            only,
+            vars,
+            profiles,
+            packs,
+            update_packs,
+            pre_push,
+            message_mode,
+            ignore_mode,
+            shared,
+            merge_strategy,
+            adopt,
+        } => {
+            if !only.is_empty() && chat {
+                bail!("--only cannot be used with --chat");
+            }
+            if !vars.is_empty() && (global || chat) {
+                bail!("--var cannot be used with --global or --chat");
+            }
+            if !profiles.is_empty() && (global || chat) {
+                bail!("--profile cannot be used with --global or --chat");
+            }
+            if !packs.is_empty() && (global || chat) {
+                bail!("--templates cannot be used with --global or --chat (list global packs in config.toml)");
+            }
+            if update_packs && chat {
+                bail!("--update-packs cannot be used with --chat");
+            }
+            if pre_push && (global || chat) {
+                bail!("--pre-push cannot be used with --global or --chat");
+            }
+            if message_mode.is_some() && (global || chat) {
+                bail!("--message-mode cannot be used with --global or --chat");
+            }
+            if ignore_mode.is_some() && (global || chat) {
+                bail!("--ignore-mode cannot be used with --global or --chat");
+            }
+            if shared && (global || chat) {
+                bail!("--shared cannot be used with --global or --chat");
+            }
+            if merge_strategy.is_some() && chat {
+                bail!("--merge-strategy cannot be used with --chat");
+            }
+            if adopt && chat {
+                bail!("--adopt cannot be used with --chat");
             }
 
             // Validate and parse category names
             let categories = commands::install::parse_categories(&only)?;
+            let vars = render::parse_vars(&vars)?;
+            let message_mode = message_mode.map(|m| m.parse()).transpose()?;
+            let ignore_mode = ignore_mode.map(|m| m.parse()).transpose()?;
+            let merge_strategy = merge_strategy.map(|m| m.parse()).transpose()?;
 
             if global {
-                commands::install::install_global(dry_run)?;
+                let filter = templates::InstallFilter::new(categories, skip);
+                let opts = commands::install::GlobalOptions { merge_strategy, adopt, update_packs };
+                commands::install::install_global(dry_run, &filter, &opts)?;
             } else if chat {
                 commands::install::install_chat(dry_run)?;
             } else {
                 let filter = templates::InstallFilter::new(categories, skip);
-                commands::install::install_project(dry_run, &filter)?;
+                let hooks = githooks::HookOptions { pre_push, message_mode };
+                let opts = commands::install::ProjectOptions {
+                    vars,
+                    profiles,
+                    packs,
+                    update_packs,
+                    hooks,
+                    ignore_mode,
+                    shared,
+                    merge_strategy,
+                    adopt,
+                };
+                commands::install::install_project(dry_run, &filter, &opts)?;
             }
         }
-        Commands::Uninstall { global, yes } => {
+        Commands::Uninstall { global, yes, dry_run, keep, purge } => {
+            if global && purge {
+                bail!("--purge cannot be used with --global (~/.claude/ also holds Claude Code's own data)");
+            }
+
+            let keep = commands::install::parse_categories(&keep)?;
+            let opts = commands::uninstall::UninstallOptions { yes, dry_run, keep, purge };
             if global {
-                commands::uninstall::uninstall_global(yes)?;
+                commands::uninstall::uninstall_global(&opts)?;
             } else {
-                commands::uninstall::uninstall_project(yes)?;
+                commands::uninstall::uninstall_project(&opts)?;
             }
         }
         Commands::Status => {
             commands::status::status()?;
         }
+        Commands::Diff { category, paths, identical } => {
+            let categories = commands::install::parse_categories(&category)?;
+            commands::diff::diff(categories, &paths, identical)?;
+        }
         Commands::Update => {
             commands::update::update()?;
         }
@@ -214,6 +452,30 @@ fn main() -> Result<()> {
                 commands::sync::sync_status()?;
             }
         },
+        Commands::Hook { command } => match command {
+            HookCommands::Run { name, args } => {
+                commands::hook::run(&name, &args)?;
+            }
+        },
+        Commands::Git { command } => match command {
+            GitCommands::Untrack { yes, dry_run, branch, message } => {
+                let opts = commands::git::UntrackOptions { dry_run, yes, branch, message };
+                commands::git::untrack(&opts)?;
+            }
+        },
+        Commands::Audit { command } => match command {
+            AuditCommands::Commits { range, fix } => {
+                commands::audit::commits(range.as_deref(), fix)?;
+            }
+        },
+        Commands::Guard { command } => match command {
+            GuardCommands::Pretooluse => {
+                commands::guard::pretooluse()?;
+            }
+            GuardCommands::Test { payload } => {
+                commands::guard::test(&payload)?;
+            }
+        },
     }
 
     Ok(())
@@ -0,0 +1,212 @@
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+/// Conflict marker opening the locally modified side
+const MARKER_LOCAL: &str = "<<<<<<< local";
+/// Conflict marker separating both sides
+const MARKER_SEPARATOR: &str = "=======";
+/// Conflict marker closing the template side
+const MARKER_TEMPLATE: &str = ">>>>>>> template";
+
+/// Result of a three-way text merge
+#[derive(Debug, PartialEq, Eq)]
+pub struct MergeResult {
+    /// Merged text (contains conflict markers if `conflicts > 0`)
+    pub text: String,
+    /// Number of hunks that could not be resolved automatically
+    pub conflicts: usize,
+}
+
+/// A changed region of `base` as seen from one side
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    base_start: usize,
+    base_end: usize,
+    side_start: usize,
+    side_end: usize,
+}
+
+/// Which side(s) changed a merged region
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Local,
+    Template,
+}
+
+/// Three-way merge of line-based text
+///
+/// `base` is the template hagi originally wrote, `local` is the file on disk,
+/// `template` is the new template. Hunks changed on only one side are taken
+/// from that side; hunks changed identically on both sides are taken once;
+/// anything else is wrapped in conflict markers:
+///
+/// ```text
+/// <<<<<<< local
+/// (local lines)
+/// =======
+/// (template lines)
+/// >>>>>>> template
+/// ```
+pub fn merge3(base: &str, local: &str, template: &str) -> MergeResult {
+    let base_lines = split_lines(base);
+    let local_lines = split_lines(local);
+    let template_lines = split_lines(template);
+
+    let mut changes: Vec<(Side, Change)> = Vec::new();
+    changes.extend(diff_changes(&base_lines, &local_lines).into_iter().map(|c| (Side::Local, c)));
+    changes.extend(diff_changes(&base_lines, &template_lines).into_iter().map(|c| (Side::Template, c)));
+    changes.sort_by_key(|(_, c)| (c.base_start, c.base_end));
+
+    let mut text = String::new();
+    let mut conflicts = 0;
+    let mut base_pos = 0;
+    // Line offset of each side relative to base, before the current region
+    let mut local_offset: isize = 0;
+    let mut template_offset: isize = 0;
+
+    let mut i = 0;
+    while i < changes.len() {
+        // Grow a region from all changes that overlap or touch it
+        let region_start = changes[i].1.base_start;
+        let mut region_end = changes[i].1.base_end;
+        let mut j = i + 1;
+        while j < changes.len() && changes[j].1.base_start <= region_end {
+            region_end = region_end.max(changes[j].1.base_end);
+            j += 1;
+        }
+        let region = &changes[i..j];
+
+        for line in &base_lines[base_pos..region_start] {
+            text.push_str(line);
+        }
+
+        let local_delta = side_delta(region, Side::Local);
+        let template_delta = side_delta(region, Side::Template);
+        let local_part = side_slice(&local_lines, region_start, region_end, local_offset, local_delta);
+        let template_part =
+            side_slice(&template_lines, region_start, region_end, template_offset, template_delta);
+
+        let local_changed = region.iter().any(|(side, _)| *side == Side::Local);
+        let template_changed = region.iter().any(|(side, _)| *side == Side::Template);
+
+        if !template_changed || local_part == template_part {
+            push_lines(&mut text, local_part);
+        } else if !local_changed {
+            push_lines(&mut text, template_part);
+        } else {
+            conflicts += 1;
+            push_marker(&mut text, MARKER_LOCAL);
+            push_lines_terminated(&mut text, local_part);
+            push_marker(&mut text, MARKER_SEPARATOR);
+            push_lines_terminated(&mut text, template_part);
+            push_marker(&mut text, MARKER_TEMPLATE);
+        }
+
+        local_offset += local_delta;
+        template_offset += template_delta;
+        base_pos = region_end;
+        i = j;
+    }
+
+    for line in &base_lines[base_pos..] {
+        text.push_str(line);
+    }
+
+    MergeResult { text, conflicts }
+}
+
+/// Split text into lines, keeping line terminators
+fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Non-equal diff operations between base and one side
+fn diff_changes(base: &[&str], side: &[&str]) -> Vec<Change> {
+    capture_diff_slices(Algorithm::Myers, base, side)
+        .iter()
+        .map(|op| op.as_tag_tuple())
+        .filter(|(tag, _, _)| *tag != DiffTag::Equal)
+        .map(|(_, old, new)| Change {
+            base_start: old.start,
+            base_end: old.end,
+            side_start: new.start,
+            side_end: new.end,
+        })
+        .collect()
+}
+
+/// Net change in line count contributed by one side within a region
+fn side_delta(region: &[(Side, Change)], side: Side) -> isize {
+    region
+        .iter()
+        .filter(|(s, _)| *s == side)
+        .map(|(_, c)| (c.side_end - c.side_start) as isize - (c.base_end - c.base_start) as isize)
+        .sum()
+}
+
+/// Lines of one side corresponding to base range `[start, end)`
+fn side_slice<'a, 'b>(
+    lines: &'b [&'a str],
+    start: usize,
+    end: usize,
+    offset: isize,
+    delta: isize,
+) -> &'b [&'a str] {
+    let side_start = (start as isize + offset) as usize;
+    let side_end = (end as isize + offset + delta) as usize;
+    &lines[side_start..side_end]
+}
+
+fn push_lines(text: &mut String, lines: &[&str]) {
+    for line in lines {
+        text.push_str(line);
+    }
+}
+
+/// Push lines, making sure the last one ends with a newline (so a marker can follow)
+fn push_lines_terminated(text: &mut String, lines: &[&str]) {
+    push_lines(text, lines);
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+fn push_marker(text: &mut String, marker: &str) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(marker);
+    text.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge3_takes_changes_from_both_sides() {
+        let base = "a\nb\nc\nd\n";
+        let local = "a\nB\nc\nd\n";
+        let template = "a\nb\nc\nD\n";
+
+        let result = merge3(base, local, template);
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.text, "a\nB\nc\nD\n");
+    }
+
+    #[test]
+    fn test_merge3_identical_changes_are_not_conflicts() {
+        let result = merge3("a\nb\n", "a\nx\n", "a\nx\n");
+        assert_eq!(result.conflicts, 0);
+        assert_eq!(result.text, "a\nx\n");
+    }
+
+    #[test]
+    fn test_merge3_conflict_markers() {
+        let result = merge3("a\nb\nc\n", "a\nlocal\nc\n", "a\ntemplate\nc\n");
+        assert_eq!(result.conflicts, 1);
+        assert_eq!(
+            result.text,
+            "a\n<<<<<<< local\nlocal\n=======\ntemplate\n>>>>>>> template\nc\n"
+        );
+    }
+}
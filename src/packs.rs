@@ -0,0 +1,376 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::lock::{self, InstallLock, PackPin};
+use crate::templates::{TemplateOrigin, TemplateSet};
+use crate::utils;
+
+/// User configuration file listing template packs (inside the hagi config directory)
+const CONFIG_FILE: &str = "config.toml";
+
+/// Pack cache directory (inside the hagi cache directory)
+const PACKS_CACHE_DIR: &str = "packs";
+
+/// Subdirectory used as the template root when a pack contains it
+const PACK_TEMPLATE_DIR: &str = ".claude";
+
+#[derive(Debug, Default, Deserialize)]
+struct UserConfig {
+    #[serde(default)]
+    packs: Vec<String>,
+}
+
+/// When to contact the remote of a git pack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FetchPolicy {
+    /// Reuse the pinned commit when there is one (fetch only if it is not cached)
+    Pinned,
+    /// Fetch and move to the latest commit of the requested ref
+    Update,
+}
+
+/// A pack ready to be layered over the embedded templates
+#[derive(Debug, Clone)]
+pub struct ResolvedPack {
+    /// Pack source as given (`<dir|git-url>[#ref]`)
+    pub source: String,
+    /// Directory mirroring the `.claude` template tree
+    pub root: PathBuf,
+    /// Checked out commit (git packs only)
+    pub commit: Option<String>,
+    pub configured: bool,
+}
+
+/// Where a pack comes from
+enum PackLocation<'a> {
+    Dir(PathBuf),
+    Git { url: &'a str, reference: Option<&'a str> },
+}
+
+/// Resolve the template packs for an install
+///
+/// Precedence (lowest first): packs from `~/.config/hagi/config.toml`, then packs
+/// given with `--templates` (or remembered from the last `--templates`), each in
+/// the order listed. Packs given on the command line are always updated.
+pub fn resolve_packs(
+    requested: &[String],
+    install_lock: &InstallLock,
+    policy: FetchPolicy,
+) -> Result<Vec<ResolvedPack>> {
+    let configured = load_configured_packs()?;
+    let cache_root = utils::hagi_cache_dir()?.join(PACKS_CACHE_DIR);
+
+    let mut sources: Vec<(String, bool)> = configured.iter().map(|s| (s.clone(), true)).collect();
+    if requested.is_empty() {
+        sources.extend(
+            install_lock
+                .packs
+                .iter()
+                .filter(|p| !p.configured && !configured.contains(&p.source))
+                .map(|p| (p.source.clone(), false)),
+        );
+    } else {
+        sources.extend(requested.iter().map(|s| (s.clone(), false)));
+    }
+
+    let mut packs = Vec::new();
+    for (source, is_configured) in sources {
+        if packs.iter().any(|p: &ResolvedPack| p.source == source) {
+            continue;
+        }
+        let pinned = install_lock
+            .packs
+            .iter()
+            .find(|p| p.source == source)
+            .and_then(|p| p.commit.as_deref());
+        let policy = if requested.contains(&source) { FetchPolicy::Update } else { policy };
+        let pack = resolve_pack(&source, pinned, policy, &cache_root)
+            .with_context(|| format!("Failed to load template pack: {}", source))?;
+        packs.push(ResolvedPack { configured: is_configured, ..pack });
+    }
+    Ok(packs)
+}
+
+/// Lock entries for resolved packs
+pub fn pins(packs: &[ResolvedPack]) -> Vec<PackPin> {
+    packs
+        .iter()
+        .map(|p| PackPin {
+            source: p.source.clone(),
+            commit: p.commit.clone(),
+            configured: p.configured,
+        })
+        .collect()
+}
+
+/// Embedded templates with the packs, then the user overlay, layered on top
+pub fn build_template_set(packs: &[ResolvedPack]) -> Result<TemplateSet> {
+    let mut set = TemplateSet::embedded();
+    for pack in packs {
+        set.layer_dir(&pack.root, TemplateOrigin::Pack(pack.source.clone()))?;
+    }
+    let overlaid = set.layer_overlay()?;
+    if overlaid > 0 {
+        println!(
+            "{} ~/.config/hagi/templates/ {}",
+            "Template overlay:".cyan(),
+            format!("({} files)", overlaid).dimmed()
+        );
+    }
+    Ok(set)
+}
+
+/// Print the packs in effect
+pub fn print_packs(packs: &[ResolvedPack]) {
+    if packs.is_empty() {
+        return;
+    }
+    println!("{}", "Template packs:".cyan());
+    for pack in packs {
+        let detail = match &pack.commit {
+            Some(commit) => format!("@ {}", &commit[..commit.len().min(12)]),
+            None => "directory".to_string(),
+        };
+        println!("  - {} {}", pack.source, detail.dimmed());
+    }
+}
+
+/// Pack sources listed in `~/.config/hagi/config.toml`
+fn load_configured_packs() -> Result<Vec<String>> {
+    let path = utils::hagi_config_dir()?.join(CONFIG_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let config: UserConfig = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+    Ok(config.packs)
+}
+
+/// Resolve one pack; git packs are checked out under `cache_root`
+fn resolve_pack(source: &str, pinned: Option<&str>, policy: FetchPolicy, cache_root: &Path) -> Result<ResolvedPack> {
+    let (root, commit) = match parse_location(source) {
+        PackLocation::Dir(dir) => {
+            if !dir.is_dir() {
+                bail!("Directory not found: {}", dir.display());
+            }
+            (dir, None)
+        }
+        PackLocation::Git { url, reference } => {
+            let cache = cache_path(cache_root, url);
+            let (dir, commit) = checkout_git_pack(&cache, url, reference, pinned, policy)?;
+            (dir, Some(commit))
+        }
+    };
+
+    let nested = root.join(PACK_TEMPLATE_DIR);
+    let root = if nested.is_dir() { nested } else { root };
+
+    Ok(ResolvedPack {
+        source: source.to_string(),
+        root,
+        commit,
+        configured: false,
+    })
+}
+
+/// Split `<location>[#ref]` and decide whether it is a plain directory or a git repository
+///
+/// An existing directory whose path contains `#` is taken as is, not as `<dir>#<ref>`.
+fn parse_location(source: &str) -> PackLocation<'_> {
+    let whole = expand_home(source);
+    if whole.is_dir() && !is_bare_repository(&whole) {
+        return PackLocation::Dir(whole);
+    }
+
+    let (location, reference) = match source.rsplit_once('#') {
+        Some((location, reference)) if !reference.is_empty() => (location, Some(reference)),
+        _ => (source, None),
+    };
+
+    let path = expand_home(location);
+    let is_git = location.contains("://")
+        || location.starts_with("git@")
+        || location.ends_with(".git")
+        || reference.is_some()
+        || is_bare_repository(&path);
+
+    if is_git {
+        PackLocation::Git { url: location, reference }
+    } else {
+        PackLocation::Dir(path)
+    }
+}
+
+fn expand_home(location: &str) -> PathBuf {
+    if let Some(rest) = location.strip_prefix("~/")
+        && let Ok(home) = utils::home_dir()
+    {
+        return home.join(rest);
+    }
+    PathBuf::from(location)
+}
+
+fn is_bare_repository(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
+
+/// Clone or update the checkout of a git pack in `cache`; returns (checkout dir, commit)
+fn checkout_git_pack(
+    cache: &Path,
+    url: &str,
+    reference: Option<&str>,
+    pinned: Option<&str>,
+    policy: FetchPolicy,
+) -> Result<(PathBuf, String)> {
+    let cache = cache.to_path_buf();
+    let git_url = if url.starts_with("~/") {
+        expand_home(url).to_string_lossy().to_string()
+    } else {
+        url.to_string()
+    };
+
+    let mut fetched = false;
+    if !cache.join(".git").exists() {
+        let parent = cache.parent().context("Invalid pack cache path")?;
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        println!("{} {}", "Cloning:".green(), url);
+        let cache_str = cache.to_string_lossy();
+        utils::git(parent, &["clone", "--quiet", "--no-checkout", &git_url, &cache_str])?;
+        fetched = true;
+    }
+
+    let commit = match pinned.filter(|_| policy == FetchPolicy::Pinned) {
+        Some(commit) => {
+            if !has_commit(&cache, commit) && !fetched {
+                fetch(&cache, url)?;
+            }
+            commit.to_string()
+        }
+        None => {
+            if !fetched {
+                fetch(&cache, url)?;
+            }
+            resolve_ref(&cache, reference)?
+        }
+    };
+
+    if !has_commit(&cache, &commit) {
+        bail!("Commit {} not found in {}", commit, url);
+    }
+
+    utils::git(&cache,
+        &["-c", "advice.detachedHead=false", "checkout", "--quiet", "--force", "--detach", &commit],
+    )?;
+    utils::git(&cache, &["clean", "-fdxq"])?;
+
+    Ok((cache, commit))
+}
+
+/// Cache directory for a pack URL: `<name>-<hash>` under `cache_root` (`~/.cache/hagi/packs`)
+fn cache_path(cache_root: &Path, url: &str) -> PathBuf {
+    let name: String = url
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .rsplit(['/', ':'])
+        .next()
+        .unwrap_or("pack")
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let hash = lock::content_hash(url.as_bytes());
+    cache_root.join(format!("{}-{}", name, &hash[..12]))
+}
+
+fn fetch(cache: &Path, url: &str) -> Result<()> {
+    println!("{} {}", "Fetching:".green(), url);
+    utils::git(cache,
+        &["fetch", "--quiet", "--tags", "--force", "origin", "+refs/heads/*:refs/remotes/origin/*"],
+    )?;
+    // Keep origin/HEAD in step with the remote default branch (ignore failure for odd remotes)
+    let _ = utils::git(cache, &["remote", "set-head", "origin", "--auto"]);
+    Ok(())
+}
+
+/// Resolve a branch, tag or commit (default: the remote HEAD) to a commit hash
+fn resolve_ref(cache: &Path, reference: Option<&str>) -> Result<String> {
+    let candidates = match reference {
+        Some(reference) => vec![format!("refs/remotes/origin/{}", reference), reference.to_string()],
+        None => vec!["refs/remotes/origin/HEAD".to_string(), "HEAD".to_string()],
+    };
+
+    for candidate in &candidates {
+        if let Ok(commit) = utils::git(cache,
+            &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", candidate)],
+        ) {
+            return Ok(commit);
+        }
+    }
+    bail!("Unknown ref: {}", reference.unwrap_or("HEAD"))
+}
+
+fn has_commit(cache: &Path, commit: &str) -> bool {
+    utils::git(cache, &["cat-file", "-e", &format!("{}^{{commit}}", commit)]).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Commit `content` as `instructions/rules.md` in the work repo and push it to the bare one
+    fn commit_and_push(work: &Path, content: &str) -> String {
+        fs::create_dir_all(work.join(".claude/instructions")).unwrap();
+        fs::write(work.join(".claude/instructions/rules.md"), content).unwrap();
+        utils::git(work, &["add", "-A"]).unwrap();
+        utils::git(work, &["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", content]).unwrap();
+        utils::git(work, &["push", "-q", "origin", "HEAD:main"]).unwrap();
+        utils::git(work, &["rev-parse", "HEAD"]).unwrap()
+    }
+
+    fn rules(pack: &ResolvedPack) -> String {
+        fs::read_to_string(pack.root.join("instructions/rules.md")).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_directory_pack() {
+        let tmp = tempfile::tempdir().unwrap();
+        // '#' in a directory name is not a ref separator
+        let dir = tmp.path().join("team#packs");
+        fs::create_dir_all(dir.join(".claude/skills")).unwrap();
+        let source = dir.to_string_lossy().to_string();
+
+        let pack = resolve_pack(&source, None, FetchPolicy::Pinned, &tmp.path().join("cache")).unwrap();
+        assert_eq!(pack.root, dir.join(".claude"));
+        assert_eq!(pack.commit, None);
+        assert!(matches!(parse_location("https://example.com/packs.git#v1"), PackLocation::Git { reference: Some("v1"), .. }));
+    }
+
+    #[test]
+    fn test_git_pack_pin_and_update() {
+        let tmp = tempfile::tempdir().unwrap();
+        let bare = tmp.path().join("packs.git");
+        let work = tmp.path().join("work");
+        let cache = tmp.path().join("cache");
+        utils::git(tmp.path(), &["init", "-q", "--bare", "-b", "main", &bare.to_string_lossy()]).unwrap();
+        utils::git(tmp.path(), &["clone", "-q", &bare.to_string_lossy(), &work.to_string_lossy()]).unwrap();
+        let first = commit_and_push(&work, "v1");
+
+        let url = format!("file://{}", bare.display());
+        for source in [bare.to_string_lossy().to_string(), format!("{}#main", url)] {
+            let pack = resolve_pack(&source, None, FetchPolicy::Pinned, &cache).unwrap();
+            assert_eq!(pack.commit.as_deref(), Some(first.as_str()));
+            assert_eq!(rules(&pack), "v1");
+        }
+
+        // A pinned pack stays on its commit until --update-packs moves the pin
+        let second = commit_and_push(&work, "v2");
+        let pinned = resolve_pack(&url, Some(&first), FetchPolicy::Pinned, &cache).unwrap();
+        assert_eq!((pinned.commit.as_deref(), rules(&pinned).as_str()), (Some(first.as_str()), "v1"));
+        let updated = resolve_pack(&url, Some(&first), FetchPolicy::Update, &cache).unwrap();
+        assert_eq!((updated.commit.as_deref(), rules(&updated).as_str()), (Some(second.as_str()), "v2"));
+    }
+}
@@ -0,0 +1,279 @@
+use anyhow::{bail, Context, Result};
+use globset::{Glob, GlobSetBuilder};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Embedded profile definitions
+const PROFILES_TOML: &str = include_str!("../templates/profiles.toml");
+
+/// Directories never scanned during detection
+const IGNORED_DIRS: &[&str] = &[".git", ".claude", "target", "node_modules", ".venv", "vendor"];
+
+/// A project profile (see templates/profiles.toml for field documentation)
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    #[serde(skip)]
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub detect: Vec<String>,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub templates: Vec<String>,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub ask: Vec<String>,
+    #[serde(default)]
+    pub mcp_enable: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ProfilesFile {
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// Load all embedded profiles
+pub fn load_profiles() -> Result<Vec<Profile>> {
+    let file: ProfilesFile =
+        toml::from_str(PROFILES_TOML).context("Failed to parse embedded profiles.toml")?;
+    Ok(file
+        .profiles
+        .into_iter()
+        .map(|(name, profile)| Profile { name, ..profile })
+        .collect())
+}
+
+/// How the active profiles were chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    /// Given with `--profile` (or remembered from a previous `--profile`)
+    Explicit,
+    /// Detected from project files
+    Detected,
+}
+
+/// Profiles in effect for an install
+#[derive(Debug, Clone)]
+pub struct ActiveProfiles {
+    pub active: Vec<Profile>,
+    pub selection: Selection,
+    /// Template paths owned by profiles that are not active
+    excluded_templates: Vec<String>,
+}
+
+impl ActiveProfiles {
+    /// Resolve profiles: explicit names if given, otherwise detection
+    pub fn resolve(project_dir: &Path, requested: &[String]) -> Result<Self> {
+        let all = load_profiles()?;
+
+        let (active, selection) = if requested.is_empty() {
+            let files = scan_project_files(project_dir);
+            let detected = all.iter().filter(|p| matches_any(&p.detect, &files)).cloned().collect();
+            (detected, Selection::Detected)
+        } else {
+            let mut active = Vec::new();
+            for name in requested {
+                let Some(profile) = all.iter().find(|p| &p.name == name) else {
+                    let valid: Vec<&str> = all.iter().map(|p| p.name.as_str()).collect();
+                    bail!("Unknown profile: '{}'\nValid profiles: {}", name, valid.join(", "));
+                };
+                active.push(profile.clone());
+            }
+            (active, Selection::Explicit)
+        };
+
+        let excluded_templates = all
+            .iter()
+            .flat_map(|p| p.templates.iter())
+            .filter(|t| !active.iter().any(|a: &Profile| a.templates.contains(t)))
+            .cloned()
+            .collect();
+
+        Ok(Self { active, selection, excluded_templates })
+    }
+
+    /// No profiles (chat mode)
+    pub fn none() -> Self {
+        Self {
+            active: Vec::new(),
+            selection: Selection::Explicit,
+            excluded_templates: Vec::new(),
+        }
+    }
+
+    /// No profiles, leaving out every profile's templates (global install: no project stack)
+    pub fn user_level() -> Result<Self> {
+        let excluded_templates = load_profiles()?.into_iter().flat_map(|p| p.templates).collect();
+        Ok(Self {
+            active: Vec::new(),
+            selection: Selection::Explicit,
+            excluded_templates,
+        })
+    }
+
+    /// Template paths that must not be installed (owned by inactive profiles)
+    pub fn excluded_templates(&self) -> &[String] {
+        &self.excluded_templates
+    }
+
+    /// Active profile names
+    pub fn names(&self) -> Vec<&str> {
+        self.active.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    /// Language of the first active profile (value of `{{language}}`)
+    pub fn language(&self) -> &str {
+        self.active
+            .iter()
+            .find_map(|p| p.language.as_deref())
+            .unwrap_or("")
+    }
+
+    /// Apply profile permission rules and MCP defaults to a rendered template
+    ///
+    /// Only `settings.local.json` and `mcp.json` are changed; other content is returned as-is.
+    pub fn apply(&self, relative_path: &Path, content: String) -> Result<String> {
+        if self.active.is_empty() {
+            return Ok(content);
+        }
+
+        let changed = match relative_path.to_str() {
+            Some("settings.local.json") => {
+                let mut settings: serde_json::Value = serde_json::from_str(&content)
+                    .context("Failed to parse settings.local.json template")?;
+                self.add_permissions(&mut settings).then_some(settings)
+            }
+            Some("mcp.json") => {
+                let mut mcp: serde_json::Value =
+                    serde_json::from_str(&content).context("Failed to parse mcp.json template")?;
+                self.enable_mcp_servers(&mut mcp).then_some(mcp)
+            }
+            _ => None,
+        };
+
+        match changed {
+            Some(value) => Ok(format!(
+                "{}\n",
+                serde_json::to_string_pretty(&value).context("Failed to serialize JSON")?
+            )),
+            None => Ok(content),
+        }
+    }
+
+    /// Append profile permission rules (deduplicated); returns true if anything was added
+    fn add_permissions(&self, settings: &mut serde_json::Value) -> bool {
+        let mut changed = false;
+        for profile in &self.active {
+            for (kind, rules) in [("allow", &profile.allow), ("deny", &profile.deny), ("ask", &profile.ask)] {
+                if rules.is_empty() {
+                    continue;
+                }
+                let Some(permissions) = settings
+                    .as_object_mut()
+                    .map(|o| o.entry("permissions").or_insert_with(|| serde_json::json!({})))
+                    .and_then(|p| p.as_object_mut())
+                else {
+                    continue;
+                };
+                let Some(list) = permissions
+                    .entry(kind)
+                    .or_insert_with(|| serde_json::json!([]))
+                    .as_array_mut()
+                else {
+                    continue;
+                };
+                for rule in rules {
+                    if !list.iter().any(|r| r.as_str() == Some(rule.as_str())) {
+                        list.push(serde_json::Value::String(rule.clone()));
+                        changed = true;
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// Remove `disabled` from servers the profiles enable; returns true if anything changed
+    fn enable_mcp_servers(&self, mcp: &mut serde_json::Value) -> bool {
+        let mut changed = false;
+        let Some(servers) = mcp.get_mut("mcpServers").and_then(|s| s.as_object_mut()) else {
+            return false;
+        };
+        for name in self.active.iter().flat_map(|p| p.mcp_enable.iter()) {
+            if let Some(server) = servers.get_mut(name).and_then(|s| s.as_object_mut())
+                && server.remove("disabled").is_some()
+            {
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Relative paths of files in the project root and one directory level below
+fn scan_project_files(project_dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let Ok(entries) = fs::read_dir(project_dir) else {
+        return files;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = entry.file_name();
+        if path.is_dir() {
+            if IGNORED_DIRS.iter().any(|d| name == *d) {
+                continue;
+            }
+            if let Ok(sub_entries) = fs::read_dir(&path) {
+                for sub in sub_entries.flatten() {
+                    if sub.path().is_file() {
+                        files.push(PathBuf::from(&name).join(sub.file_name()));
+                    }
+                }
+            }
+        } else {
+            files.push(PathBuf::from(name));
+        }
+    }
+    files
+}
+
+/// Check if any file matches any glob (`*` does not cross `/`)
+fn matches_any(patterns: &[String], files: &[PathBuf]) -> bool {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        if let Ok(glob) = globset::GlobBuilder::new(pattern).literal_separator(true).build() {
+            builder.add(glob);
+        } else if let Ok(glob) = Glob::new(pattern) {
+            builder.add(glob);
+        }
+    }
+    let Ok(set) = builder.build() else {
+        return false;
+    };
+    files.iter().any(|f| set.is_match(f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_profiles_parse() {
+        let profiles = load_profiles().unwrap();
+        assert!(profiles.iter().any(|p| p.name == "rust"));
+    }
+
+    #[test]
+    fn test_matches_any_does_not_cross_directories() {
+        let files = vec![PathBuf::from("scripts/build.sh")];
+        assert!(!matches_any(&["*.sh".to_string()], &files));
+        assert!(matches_any(&["*/*.sh".to_string()], &files));
+    }
+}
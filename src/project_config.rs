@@ -0,0 +1,238 @@
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::ignore::IgnoreMode;
+use crate::utils;
+
+/// Project configuration file (at the repository root, meant to be committed)
+pub const PROJECT_CONFIG_FILE: &str = ".hagi.toml";
+
+/// Branches protected when nothing is configured
+const DEFAULT_PROTECTED_BRANCHES: &[&str] = &["main", "master"];
+
+/// Commit message patterns that are always rejected (regular expressions, Rust `regex` syntax)
+pub const SIGNATURE_PATTERNS: &[&str] = &[
+    r"Generated with \[Claude Code\]",
+    r"Co-Authored-By: Claude",
+];
+
+/// Project settings from `.hagi.toml`, overridden by `git config hagi.*`
+///
+/// ```toml
+/// ignore_mode = "exclude"
+///
+/// [hooks]
+/// protected_branches = ["main", "release/*"]
+/// forbidden_patterns = ["WIP"]
+/// subject_pattern = "^(🌱|✨|🔄|🐛|📝|♻️|🧪|🔧) "
+/// subject_max_length = 50
+/// pre_push = true
+/// message_mode = "strip"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Where `hagi install` writes the ignore entries (git config: `hagi.ignoreMode`)
+    pub ignore_mode: Option<IgnoreMode>,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+}
+
+/// Rules enforced by the git hooks
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Branch names or globs (`release/*`) that reject direct commits
+    /// (git config: `hagi.protectedBranch`, multi-valued)
+    pub protected_branches: Option<Vec<String>>,
+    /// Extra regexes rejected in commit messages, on top of the signature patterns
+    /// (git config: `hagi.forbiddenPattern`, multi-valued)
+    #[serde(default)]
+    pub forbidden_patterns: Vec<String>,
+    /// Regex the commit subject must match (git config: `hagi.subjectPattern`)
+    pub subject_pattern: Option<String>,
+    /// Maximum commit subject length in characters (git config: `hagi.subjectMaxLength`)
+    pub subject_max_length: Option<usize>,
+    /// Install the pre-push hook (git config: `hagi.prePush`)
+    pub pre_push: Option<bool>,
+    /// How commit messages with signatures are handled (git config: `hagi.messageMode`)
+    pub message_mode: Option<MessageMode>,
+    /// Extra regexes for lines removed in strip mode, on top of the signature patterns
+    /// (git config: `hagi.stripPattern`, multi-valued)
+    #[serde(default)]
+    pub strip_patterns: Vec<String>,
+}
+
+/// How the hooks deal with signatures in commit messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageMode {
+    /// commit-msg rejects the commit
+    Reject,
+    /// prepare-commit-msg removes the lines first; commit-msg stays as the final check
+    Strip,
+}
+
+impl fmt::Display for MessageMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageMode::Reject => write!(f, "reject"),
+            MessageMode::Strip => write!(f, "strip"),
+        }
+    }
+}
+
+impl FromStr for MessageMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(MessageMode::Reject),
+            "strip" => Ok(MessageMode::Strip),
+            _ => bail!("Unknown message mode: '{}'\nValid modes: reject, strip", s),
+        }
+    }
+}
+
+impl ProjectConfig {
+    /// Load `.hagi.toml` from the project directory and apply `git config hagi.*` overrides
+    pub fn load(project_dir: &Path) -> Result<Self> {
+        let path = project_dir.join(PROJECT_CONFIG_FILE);
+        let mut config: Self = if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            toml::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?
+        } else {
+            Self::default()
+        };
+
+        config.apply_git_config(project_dir)?;
+        Ok(config)
+    }
+
+    /// Override settings with `git config hagi.*` values (multi-valued keys replace lists)
+    fn apply_git_config(&mut self, project_dir: &Path) -> Result<()> {
+        if let Some(value) = git_config_all(project_dir, "hagi.ignoreMode").pop() {
+            self.ignore_mode = Some(value.parse().context("Invalid hagi.ignoreMode")?);
+        }
+
+        let hooks = &mut self.hooks;
+
+        let branches = git_config_all(project_dir, "hagi.protectedBranch");
+        if !branches.is_empty() {
+            hooks.protected_branches = Some(branches);
+        }
+        let patterns = git_config_all(project_dir, "hagi.forbiddenPattern");
+        if !patterns.is_empty() {
+            hooks.forbidden_patterns = patterns;
+        }
+        if let Some(pattern) = git_config_all(project_dir, "hagi.subjectPattern").pop() {
+            hooks.subject_pattern = Some(pattern);
+        }
+        if let Some(length) = git_config_all(project_dir, "hagi.subjectMaxLength").pop() {
+            let length = length
+                .parse()
+                .with_context(|| format!("Invalid hagi.subjectMaxLength: '{}'", length))?;
+            hooks.subject_max_length = Some(length);
+        }
+        if let Some(value) = git_config_all(project_dir, "hagi.prePush").pop() {
+            hooks.pre_push = Some(parse_bool(&value).with_context(|| format!("Invalid hagi.prePush: '{}'", value))?);
+        }
+        if let Some(value) = git_config_all(project_dir, "hagi.messageMode").pop() {
+            hooks.message_mode = Some(value.parse().context("Invalid hagi.messageMode")?);
+        }
+        let strip_patterns = git_config_all(project_dir, "hagi.stripPattern");
+        if !strip_patterns.is_empty() {
+            hooks.strip_patterns = strip_patterns;
+        }
+        Ok(())
+    }
+}
+
+impl HooksConfig {
+    /// Protected branch names/globs (default: main, master)
+    pub fn protected_branches(&self) -> Vec<String> {
+        match &self.protected_branches {
+            Some(branches) => branches.clone(),
+            None => DEFAULT_PROTECTED_BRANCHES.iter().map(|b| b.to_string()).collect(),
+        }
+    }
+
+    /// Signature patterns followed by the configured strip patterns
+    pub fn strip_patterns(&self) -> Vec<String> {
+        SIGNATURE_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .chain(self.strip_patterns.iter().cloned())
+            .collect()
+    }
+
+    /// Signature patterns followed by the configured forbidden patterns
+    pub fn forbidden_patterns(&self) -> Vec<String> {
+        SIGNATURE_PATTERNS
+            .iter()
+            .map(|p| p.to_string())
+            .chain(self.forbidden_patterns.iter().cloned())
+            .collect()
+    }
+}
+
+/// Parse a git config boolean
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" | "" => Ok(false),
+        _ => bail!("expected true or false"),
+    }
+}
+
+/// All values of a (possibly multi-valued) git config key
+fn git_config_all(project_dir: &Path, key: &str) -> Vec<String> {
+    utils::git_output(project_dir, &["config", "--get-all", key])
+        .map(|values| values.lines().filter(|line| !line.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_config_overrides_project_file() {
+        let repo = tempfile::tempdir().unwrap();
+        let dir = repo.path();
+        utils::init_test_repo(dir);
+        fs::write(
+            dir.join(PROJECT_CONFIG_FILE),
+            "ignore_mode = \"exclude\"\n\n[hooks]\nprotected_branches = [\"main\", \"release/*\"]\n\
+             forbidden_patterns = [\"WIP\"]\nsubject_max_length = 50\npre_push = true\n",
+        )
+        .unwrap();
+        for args in [
+            &["config", "hagi.protectedBranch", "develop"][..],
+            &["config", "--add", "hagi.protectedBranch", "trunk"],
+            &["config", "hagi.subjectMaxLength", "72"],
+            &["config", "hagi.messageMode", "strip"],
+        ] {
+            utils::git(dir, args).unwrap();
+        }
+
+        let config = ProjectConfig::load(dir).unwrap();
+        // Set only in .hagi.toml
+        assert_eq!(config.ignore_mode, Some(IgnoreMode::Exclude));
+        assert_eq!(config.hooks.forbidden_patterns, vec!["WIP"]);
+        assert_eq!(config.hooks.pre_push, Some(true));
+        // git config wins; multi-valued keys replace the whole list
+        assert_eq!(config.hooks.protected_branches(), vec!["develop", "trunk"]);
+        assert_eq!(config.hooks.subject_max_length, Some(72));
+        assert_eq!(config.hooks.message_mode, Some(MessageMode::Strip));
+        assert_eq!(config.hooks.forbidden_patterns().last().map(String::as_str), Some("WIP"));
+
+        utils::git(dir, &["config", "hagi.prePush", "maybe"]).unwrap();
+        assert!(ProjectConfig::load(dir).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::profiles::ActiveProfiles;
+use crate::utils;
+
+// ============================================================================
+// Template Variables
+// ============================================================================
+
+/// Variables available to templates at install time
+///
+/// Derived values (`project_name`, `repo_url`, `default_branch`, `language`)
+/// can be overridden with `--var key=value`.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVars {
+    values: BTreeMap<String, String>,
+}
+
+impl TemplateVars {
+    /// Derive variables from the git repository and the active profiles
+    pub fn derive(profiles: &ActiveProfiles) -> Self {
+        let mut vars = Self::default();
+        vars.set("project_name", &utils::get_repository_name());
+        vars.set("repo_url", &utils::get_remote_url().unwrap_or_default());
+        vars.set("default_branch", &utils::get_default_branch());
+        vars.set("language", profiles.language());
+        vars
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+
+    /// Override variables with user-supplied values
+    pub fn extend(&mut self, values: &BTreeMap<String, String>) {
+        for (key, value) in values {
+            self.set(key, value);
+        }
+    }
+
+    /// A variable is truthy when it is set, non-empty and not "false"/"0"
+    fn is_truthy(&self, key: &str) -> bool {
+        self.get(key).is_some_and(|v| !v.is_empty() && v != "false" && v != "0")
+    }
+}
+
+/// Parse `--var key=value` arguments
+pub fn parse_vars(args: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+    for arg in args {
+        let (key, value) = arg
+            .split_once('=')
+            .with_context(|| format!("Invalid --var '{}': expected KEY=VALUE", arg))?;
+        let key = key.trim();
+        if !is_identifier(key) {
+            bail!("Invalid --var name '{}': use letters, digits and '_'", key);
+        }
+        values.insert(key.to_string(), value.to_string());
+    }
+    Ok(values)
+}
+
+// ============================================================================
+// Rendering
+// ============================================================================
+
+/// A parsed template token
+#[derive(Debug)]
+enum Token<'a> {
+    Text(&'a str),
+    /// Variable name and the raw `{{ ... }}` source (kept when the variable is unknown)
+    Var(&'a str, &'a str),
+    If(Condition<'a>),
+    Else,
+    EndIf,
+}
+
+/// Condition of an `{{#if ...}}` block
+#[derive(Debug)]
+enum Condition<'a> {
+    Truthy(&'a str),
+    Equals(&'a str, &'a str),
+    NotEquals(&'a str, &'a str),
+}
+
+/// Render a template with variables
+///
+/// # Syntax
+/// - `{{name}}` - variable (unknown names are left as-is)
+/// - `{{#if name}}...{{else}}...{{/if}}` - set and non-empty
+/// - `{{#if name == "value"}}` / `{{#if name != "value"}}` - comparison
+///
+/// Block tags alone on a line are removed together with their line.
+pub fn render(template: &str, vars: &TemplateVars) -> Result<String> {
+    render_escaped(template, vars, false)
+}
+
+/// Render the template installed at `relative_path`
+///
+/// In JSON files variable values are escaped for a JSON string, so that a quote or a
+/// backslash (e.g. a Windows path) does not break the file.
+pub fn render_file(relative_path: &Path, template: &str, vars: &TemplateVars) -> Result<String> {
+    render_escaped(template, vars, relative_path.extension().is_some_and(|e| e == "json"))
+}
+
+/// Escape a value for use inside a JSON string literal
+fn escape_json(value: &str) -> String {
+    let quoted = serde_json::Value::from(value).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// Render with variable values escaped for JSON strings when `json` is set
+fn render_escaped(template: &str, vars: &TemplateVars, json: bool) -> Result<String> {
+    let tokens = tokenize(template)?;
+    let mut output = String::with_capacity(template.len());
+    // Stack of (condition result, currently in else branch)
+    let mut blocks: Vec<(bool, bool)> = Vec::new();
+
+    for token in tokens {
+        let active = blocks.iter().all(|(cond, in_else)| cond != in_else);
+        match token {
+            Token::Text(text) if active => output.push_str(text),
+            Token::Var(name, raw) if active => match vars.get(name) {
+                Some(value) if json => output.push_str(&escape_json(value)),
+                Some(value) => output.push_str(value),
+                None => output.push_str(raw),
+            },
+            Token::Text(_) | Token::Var(..) => {}
+            Token::If(condition) => blocks.push((evaluate(&condition, vars), false)),
+            Token::Else => match blocks.last_mut() {
+                Some((_, in_else)) if !*in_else => *in_else = true,
+                _ => bail!("Unexpected {{{{else}}}} in template"),
+            },
+            Token::EndIf => {
+                if blocks.pop().is_none() {
+                    bail!("Unexpected {{{{/if}}}} in template");
+                }
+            }
+        }
+    }
+
+    if !blocks.is_empty() {
+        bail!("Unclosed {{{{#if}}}} in template");
+    }
+
+    Ok(output)
+}
+
+fn evaluate(condition: &Condition, vars: &TemplateVars) -> bool {
+    match condition {
+        Condition::Truthy(name) => vars.is_truthy(name),
+        Condition::Equals(name, value) => vars.get(name).unwrap_or("") == *value,
+        Condition::NotEquals(name, value) => vars.get(name).unwrap_or("") != *value,
+    }
+}
+
+/// Split a template into text, variable and block tokens
+fn tokenize(template: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    // Byte offset of the first character not yet turned into a token
+    let mut pos = 0;
+    let mut search = 0;
+
+    while let Some(open) = template[search..].find("{{").map(|i| search + i) {
+        let Some(close) = template[open..].find("}}").map(|i| open + i) else {
+            break;
+        };
+        let Some(token) = parse_tag(template[open + 2..close].trim(), &template[open..close + 2])? else {
+            // Not a tag we understand (e.g. "{{ }}" in a code sample): keep verbatim
+            search = close + 2;
+            continue;
+        };
+
+        let mut text_end = open;
+        let mut next = close + 2;
+
+        // Drop block tags that stand alone on their line, including the newline
+        if !matches!(token, Token::Var(..)) {
+            let line_start = template[..open].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let after = &template[next..];
+            let newline_len = if after.starts_with("\r\n") {
+                Some(2)
+            } else if after.starts_with('\n') {
+                Some(1)
+            } else if after.is_empty() {
+                Some(0)
+            } else {
+                None
+            };
+            if line_start >= pos
+                && template[line_start..open].trim().is_empty()
+                && let Some(len) = newline_len
+            {
+                text_end = line_start;
+                next += len;
+            }
+        }
+
+        if text_end > pos {
+            tokens.push(Token::Text(&template[pos..text_end]));
+        }
+        tokens.push(token);
+        pos = next;
+        search = next;
+    }
+
+    if pos < template.len() {
+        tokens.push(Token::Text(&template[pos..]));
+    }
+    Ok(tokens)
+}
+
+/// Parse the inside of `{{ ... }}`; None if it is not template syntax
+fn parse_tag<'a>(inner: &'a str, raw: &'a str) -> Result<Option<Token<'a>>> {
+    if inner == "else" {
+        return Ok(Some(Token::Else));
+    }
+    if inner == "/if" {
+        return Ok(Some(Token::EndIf));
+    }
+    if let Some(expr) = inner.strip_prefix("#if") {
+        return parse_condition(expr.trim()).map(|c| Some(Token::If(c)));
+    }
+    if is_identifier(inner) {
+        return Ok(Some(Token::Var(inner, raw)));
+    }
+    Ok(None)
+}
+
+fn parse_condition(expr: &str) -> Result<Condition<'_>> {
+    for (op, is_equals) in [("==", true), ("!=", false)] {
+        if let Some((name, value)) = expr.split_once(op) {
+            let name = name.trim();
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .with_context(|| format!("Expected quoted value in {{{{#if {}}}}}", expr))?;
+            if !is_identifier(name) {
+                bail!("Invalid variable name in {{{{#if {}}}}}", expr);
+            }
+            return Ok(if is_equals {
+                Condition::Equals(name, value)
+            } else {
+                Condition::NotEquals(name, value)
+            });
+        }
+    }
+
+    if !is_identifier(expr) {
+        bail!("Invalid condition in {{{{#if {}}}}}", expr);
+    }
+    Ok(Condition::Truthy(expr))
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> TemplateVars {
+        let mut vars = TemplateVars::default();
+        vars.set("project_name", "hagi");
+        vars.set("language", "rust");
+        vars
+    }
+
+    #[test]
+    fn test_render_variables_and_unknown_passthrough() {
+        let output = render("# {{project_name}} {{ unknown }} {{ }}", &vars()).unwrap();
+        assert_eq!(output, "# hagi {{ unknown }} {{ }}");
+    }
+
+    #[test]
+    fn test_render_conditionals_drop_tag_lines() {
+        let template = "a\n{{#if language == \"rust\"}}\ncargo\n{{else}}\nother\n{{/if}}\n{{#if repo_url}}\nurl\n{{/if}}\nb\n";
+        assert_eq!(render(template, &vars()).unwrap(), "a\ncargo\nb\n");
+    }
+
+    #[test]
+    fn test_render_escapes_values_in_json() {
+        let mut vars = vars();
+        vars.set("project_name", r#"my "app" in C:\src"#);
+        let template = r#"{"name": "{{project_name}}"}"#;
+
+        let json = render_file(Path::new("settings.local.json"), template, &vars).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["name"], r#"my "app" in C:\src"#);
+        // Text templates get the value as is
+        let text = render_file(Path::new("CLAUDE.md"), "# {{project_name}}", &vars).unwrap();
+        assert_eq!(text, r#"# my "app" in C:\src"#);
+    }
+
+    #[test]
+    fn test_render_unclosed_block_is_error() {
+        assert!(render("{{#if language}}x", &vars()).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use anyhow::{Context, Result};
+use globset::Glob;
+use regex::Regex;
+
+use crate::project_config::HooksConfig;
+
+/// Directory that must never be committed (managed by `hagi sync`, not git)
+pub const CLAUDE_DIR_PREFIX: &str = ".claude/";
+
+/// Line git puts above the diff in `commit --verbose` (everything below is ignored)
+const SCISSORS: &str = "------------------------ >8 ------------------------";
+
+/// Commit rules compiled from the hook configuration
+#[derive(Debug)]
+pub struct CommitRules {
+    protected_branches: Vec<globset::GlobMatcher>,
+    forbidden_patterns: Vec<Regex>,
+    strip_patterns: Vec<Regex>,
+    subject_pattern: Option<Regex>,
+    subject_max_length: Option<usize>,
+}
+
+/// A rule a commit message breaks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageViolation {
+    /// Matches a forbidden pattern (signature or configured pattern)
+    Forbidden { pattern: String, line: String },
+    /// Subject does not match `subject_pattern`
+    SubjectFormat { pattern: String },
+    /// Subject is longer than `subject_max_length` characters
+    SubjectLength { max: usize, actual: usize },
+}
+
+impl CommitRules {
+    pub fn new(config: &HooksConfig) -> Result<Self> {
+        let protected_branches = config
+            .protected_branches()
+            .iter()
+            .map(|b| {
+                Glob::new(b)
+                    .map(|g| g.compile_matcher())
+                    .with_context(|| format!("Invalid protected branch pattern: '{}'", b))
+            })
+            .collect::<Result<_>>()?;
+        let forbidden_patterns = config
+            .forbidden_patterns()
+            .iter()
+            .map(|p| compile(p))
+            .collect::<Result<_>>()?;
+        let strip_patterns = config
+            .strip_patterns()
+            .iter()
+            .map(|p| compile(p))
+            .collect::<Result<_>>()?;
+        let subject_pattern = config.subject_pattern.as_deref().map(compile).transpose()?;
+
+        Ok(Self {
+            protected_branches,
+            forbidden_patterns,
+            strip_patterns,
+            subject_pattern,
+            subject_max_length: config.subject_max_length,
+        })
+    }
+
+    /// Check if direct commits to `branch` are forbidden (`*` also matches `/`, as in a shell `case`)
+    pub fn is_protected(&self, branch: &str) -> bool {
+        self.protected_branches.iter().any(|m| m.is_match(branch))
+    }
+
+    /// Forbidden patterns found in a message (signatures and configured patterns)
+    pub fn forbidden_in(&self, message: &str) -> Vec<MessageViolation> {
+        let mut violations = Vec::new();
+        for pattern in &self.forbidden_patterns {
+            if let Some(line) = message.lines().find(|l| pattern.is_match(l)) {
+                violations.push(MessageViolation::Forbidden {
+                    pattern: pattern.as_str().to_string(),
+                    line: line.trim().to_string(),
+                });
+            }
+        }
+        violations
+    }
+
+    /// All rules a commit message breaks (forbidden patterns, then subject rules)
+    pub fn check_message(&self, message: &str) -> Vec<MessageViolation> {
+        let mut violations = self.forbidden_in(message);
+        let subject = message.lines().next().unwrap_or("");
+
+        if let Some(pattern) = &self.subject_pattern
+            && !pattern.is_match(subject)
+        {
+            violations.push(MessageViolation::SubjectFormat { pattern: pattern.as_str().to_string() });
+        }
+        if let Some(max) = self.subject_max_length {
+            let actual = subject.chars().count();
+            if actual > max {
+                violations.push(MessageViolation::SubjectLength { max, actual });
+            }
+        }
+        violations
+    }
+
+    /// Remove signature lines (strip patterns) from a raw message file
+    ///
+    /// Comment lines and the verbose diff are kept as they are; blank lines left at the
+    /// end of the message text are removed. Returns the new content and the removed lines.
+    pub fn strip_signatures(&self, raw: &str, comment_char: &str) -> (String, Vec<String>) {
+        let mut removed = Vec::new();
+        let mut text: Vec<&str> = Vec::new();
+        let mut rest: Vec<&str> = Vec::new();
+
+        let mut in_diff = false;
+
+        for line in raw.lines() {
+            let is_comment = line.starts_with(comment_char);
+            in_diff = in_diff || (is_comment && line.contains(SCISSORS));
+            if !in_diff && !is_comment && self.strip_patterns.iter().any(|p| p.is_match(line)) {
+                removed.push(line.trim().to_string());
+            } else if is_comment || !rest.is_empty() {
+                rest.push(line);
+            } else {
+                text.push(line);
+            }
+        }
+        if removed.is_empty() {
+            return (raw.to_string(), removed);
+        }
+
+        while text.last().is_some_and(|l| l.trim().is_empty()) {
+            text.pop();
+        }
+        let mut content = text.join("\n");
+        content.push('\n');
+        if !rest.is_empty() {
+            content.push('\n');
+            content.push_str(&rest.join("\n"));
+            content.push('\n');
+        }
+        (content, removed)
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).with_context(|| format!("Invalid regular expression: '{}'", pattern))
+}
+
+/// Check if a repository path is inside `.claude/` (or is `.claude` itself, e.g. a shared symlink)
+pub fn is_claude_path(path: &str) -> bool {
+    path.starts_with(CLAUDE_DIR_PREFIX) || path == CLAUDE_DIR_PREFIX.trim_end_matches('/')
+}
+
+/// Commit message as git stores it: comment lines and the verbose diff removed, trimmed
+pub fn clean_message(raw: &str, comment_char: &str) -> String {
+    let mut lines = Vec::new();
+    for line in raw.lines() {
+        if line.starts_with(comment_char) && line.contains(SCISSORS) {
+            break;
+        }
+        if !line.starts_with(comment_char) {
+            lines.push(line.trim_end());
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(config: HooksConfig) -> CommitRules {
+        CommitRules::new(&config).unwrap()
+    }
+
+    #[test]
+    fn test_protected_branch_globs() {
+        let rules = rules(HooksConfig {
+            protected_branches: Some(vec!["main".into(), "release/*".into()]),
+            ..Default::default()
+        });
+        assert!(rules.is_protected("main"));
+        assert!(rules.is_protected("release/1.0"));
+        assert!(!rules.is_protected("master"));
+        assert!(!rules.is_protected("feature/main"));
+    }
+
+    #[test]
+    fn test_check_message() {
+        let rules = rules(HooksConfig {
+            forbidden_patterns: vec!["WIP".into()],
+            subject_pattern: Some("^(✨|🐛) ".into()),
+            subject_max_length: Some(10),
+            ..Default::default()
+        });
+        assert!(rules.check_message("✨ add x").is_empty());
+        assert_eq!(
+            rules.check_message("✨ add x\n\nCo-Authored-By: Claude <noreply@anthropic.com>").len(),
+            1
+        );
+        assert_eq!(
+            rules.check_message("WIP something long"),
+            vec![
+                MessageViolation::Forbidden { pattern: "WIP".into(), line: "WIP something long".into() },
+                MessageViolation::SubjectFormat { pattern: "^(✨|🐛) ".into() },
+                MessageViolation::SubjectLength { max: 10, actual: 18 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_signatures() {
+        let rules = rules(HooksConfig::default());
+        let raw = "✨ add x\n\nbody\n\n🤖 Generated with [Claude Code](https://claude.com/claude-code)\n\n\
+                   Co-Authored-By: Claude <noreply@anthropic.com>\n# comment\n";
+        let (stripped, removed) = rules.strip_signatures(raw, "#");
+        assert_eq!(stripped, "✨ add x\n\nbody\n\n# comment\n");
+        assert_eq!(removed.len(), 2);
+        assert_eq!(rules.strip_signatures("plain\n", "#").0, "plain\n");
+    }
+
+    #[test]
+    fn test_clean_message() {
+        let raw = "✨ add x\n# Please enter the commit message\n\nbody\n\
+                   # ------------------------ >8 ------------------------\ndiff --git a b\n";
+        assert_eq!(clean_message(raw, "#"), "✨ add x\n\nbody");
+    }
+}
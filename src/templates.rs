@@ -3,26 +3,40 @@ use colored::*;
 use include_dir::{include_dir, Dir};
 use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 
+use crate::json_merge::{self, MergeStrategy};
+use crate::keep;
+use crate::lock::{self, InstallLock};
+use crate::profiles::ActiveProfiles;
+use crate::render::{self, TemplateVars};
 use crate::utils;
 
 /// Embedded template directory
 pub static TEMPLATES: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/.claude");
 
+/// Embedded user-level templates for `~/.claude` (layered over `TEMPLATES` by `--global`)
+pub static GLOBAL_TEMPLATES: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/global");
+
 /// Embedded chat template directory
 pub static CHAT_TEMPLATES: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates/.chat");
 
 /// CLAUDE.md filename (used across install, status, sync)
 pub const CLAUDE_MD: &str = "CLAUDE.md";
 
+/// Settings template (`.claude/settings.local.json`; merged into `~/.claude/settings.json` globally)
+pub const SETTINGS_TEMPLATE: &str = "settings.local.json";
+
 // ============================================================================
 // Category System
 // ============================================================================
 
 /// Template categories for selective installation
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Category {
     Instructions,
     Skills,
@@ -30,6 +44,7 @@ pub enum Category {
     Config,
     Docs,
     Designs,
+    Agents,
 }
 
 impl Category {
@@ -41,6 +56,16 @@ impl Category {
         Category::Config,
         Category::Docs,
         Category::Designs,
+        Category::Agents,
+    ];
+
+    /// Categories `hagi install --global` installs into ~/.claude/ (config = settings.json)
+    pub const GLOBAL: &[Category] = &[
+        Category::Instructions,
+        Category::Skills,
+        Category::Agents,
+        Category::Config,
+        Category::Docs,
     ];
 
     /// Category name as string
@@ -52,9 +77,15 @@ impl Category {
             Category::Config => "config",
             Category::Docs => "docs",
             Category::Designs => "designs",
+            Category::Agents => "agents",
         }
     }
 
+    /// Find the category a template path belongs to
+    pub fn for_path(path: &Path) -> Option<Category> {
+        Category::ALL.iter().find(|c| c.matches_path(path)).copied()
+    }
+
     /// Check if a template path belongs to this category
     fn matches_path(&self, path: &Path) -> bool {
         let path_str = path.to_string_lossy();
@@ -69,6 +100,7 @@ impl Category {
                 path_str == CLAUDE_MD || path_str == "TODO.md"
             }
             Category::Designs => path_str.starts_with("designs/"),
+            Category::Agents => path_str.starts_with("agents/"),
         }
     }
 }
@@ -105,11 +137,19 @@ pub struct InstallFilter {
     pub only: Vec<Category>,
     /// Skip these paths (applied on top of `only`)
     pub skip: Vec<String>,
+    /// Paths silently left out (templates of inactive profiles)
+    pub exclude: Vec<String>,
 }
 
 impl InstallFilter {
     pub fn new(only: Vec<Category>, skip: Vec<String>) -> Self {
-        Self { only, skip }
+        Self { only, skip, exclude: Vec::new() }
+    }
+
+    /// Leave out the templates of profiles that are not active
+    pub fn with_profiles(mut self, profiles: &ActiveProfiles) -> Self {
+        self.exclude = profiles.excluded_templates().to_vec();
+        self
     }
 
     /// Whether `--only` was specified
@@ -127,7 +167,7 @@ impl InstallFilter {
 
     /// Check if a template path should be installed
     fn should_include(&self, path: &Path) -> bool {
-        if should_skip(path, &self.skip) {
+        if should_skip(path, &self.skip) || should_skip(path, &self.exclude) {
             return false;
         }
 
@@ -143,110 +183,590 @@ impl InstallFilter {
 // Template Access
 // ============================================================================
 
-/// Get a specific template file content
-pub fn get_template(filename: &str) -> Result<&'static str> {
-    TEMPLATES
-        .get_file(filename)
-        .and_then(|f| f.contents_utf8())
-        .with_context(|| format!("Template not found: {}", filename))
+/// User overlay directory (`~/.config/hagi/templates`)
+const OVERLAY_DIR: &str = "templates";
+
+/// Get the user template overlay directory
+pub fn overlay_dir() -> Result<PathBuf> {
+    Ok(utils::hagi_config_dir()?.join(OVERLAY_DIR))
 }
 
 // ============================================================================
-// Template Copying (with filter)
+// Template Sets (embedded templates + layers)
 // ============================================================================
 
-/// Copy templates to target directory with category filter
-pub fn copy_all_templates_filtered(target_dir: &Path, dry_run: bool, filter: &InstallFilter) -> Result<()> {
-    copy_dir_recursive(&TEMPLATES, target_dir, dry_run, filter)
+/// Where a template file comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateOrigin {
+    Embedded,
+    /// Template pack (source as given)
+    Pack(String),
+    /// User overlay (`~/.config/hagi/templates`)
+    Overlay,
+}
+
+impl fmt::Display for TemplateOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateOrigin::Embedded => write!(f, "embedded"),
+            TemplateOrigin::Pack(source) => write!(f, "pack {}", source),
+            TemplateOrigin::Overlay => write!(f, "overlay"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TemplateFile {
+    content: String,
+    origin: TemplateOrigin,
+}
+
+/// A template tree: the embedded templates with optional layers on top
+///
+/// A layer file replaces the file with the same relative path; new files are added
+/// (and fall into the `Category` their path matches). Non-UTF-8 files are skipped,
+/// as they cannot be rendered.
+#[derive(Debug, Clone, Default)]
+pub struct TemplateSet {
+    files: BTreeMap<PathBuf, TemplateFile>,
+}
+
+impl TemplateSet {
+    /// The embedded `.claude` templates
+    pub fn embedded() -> Self {
+        Self::from_embedded(&TEMPLATES)
+    }
+
+    /// Templates for `~/.claude`: the global categories of this set (embedded templates,
+    /// packs, overlay) with `GLOBAL_TEMPLATES` on top
+    ///
+    /// The project CLAUDE.md of every layer is replaced by the global one. Of the JSON
+    /// configs only `SETTINGS_TEMPLATE` is kept (merged into `settings.json`, not copied);
+    /// the project TODO.md is left out.
+    pub fn into_global(mut self) -> Self {
+        self.files.remove(Path::new(CLAUDE_MD));
+        self.add_embedded(&GLOBAL_TEMPLATES);
+        self.files.retain(|path, _| {
+            let category = Category::for_path(path);
+            path == Path::new(SETTINGS_TEMPLATE)
+                || category.is_some_and(|c| c != Category::Config && Category::GLOBAL.contains(&c))
+                    && path != Path::new("TODO.md")
+        });
+        self
+    }
+
+    fn from_embedded(dir: &Dir) -> Self {
+        let mut set = Self::default();
+        set.add_embedded(dir);
+        set
+    }
+
+    fn add_embedded(&mut self, dir: &Dir) {
+        for entry in dir.entries() {
+            match entry {
+                include_dir::DirEntry::Dir(sub_dir) => self.add_embedded(sub_dir),
+                include_dir::DirEntry::File(file) => {
+                    if let Some(content) = file.contents_utf8() {
+                        self.files.insert(
+                            file.path().to_path_buf(),
+                            TemplateFile { content: content.to_string(), origin: TemplateOrigin::Embedded },
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Layer a directory mirroring the template tree over the current set
+    ///
+    /// Returns the number of files taken from the layer.
+    pub fn layer_dir(&mut self, root: &Path, origin: TemplateOrigin) -> Result<usize> {
+        let mut files = Vec::new();
+        collect_layer_files(root, root, &mut files)?;
+
+        let count = files.len();
+        for (relative_path, content) in files {
+            self.files.insert(relative_path, TemplateFile { content, origin: origin.clone() });
+        }
+        Ok(count)
+    }
+
+    /// Layer the user overlay directory, if it exists; returns the number of files taken
+    pub fn layer_overlay(&mut self) -> Result<usize> {
+        let dir = overlay_dir()?;
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+        self.layer_dir(&dir, TemplateOrigin::Overlay)
+    }
+
+    /// Get a template file content
+    pub fn get(&self, filename: &str) -> Result<&str> {
+        self.files
+            .get(Path::new(filename))
+            .map(|f| f.content.as_str())
+            .with_context(|| format!("Template not found: {}", filename))
+    }
+
+    /// Where a template file comes from
+    pub fn origin(&self, relative_path: &Path) -> Option<&TemplateOrigin> {
+        self.files.get(relative_path).map(|f| &f.origin)
+    }
+
+    /// List template files matching the filter as (relative path, content)
+    pub fn files(&self, filter: &InstallFilter) -> Vec<(&Path, &str)> {
+        self.files
+            .iter()
+            .filter(|(path, _)| filter.should_include(path))
+            .map(|(path, file)| (path.as_path(), file.content.as_str()))
+            .collect()
+    }
 }
 
-/// Recursively copy directory structure from embedded templates
-fn copy_dir_recursive(dir: &Dir, target_base: &Path, dry_run: bool, filter: &InstallFilter) -> Result<()> {
-    for entry in dir.entries() {
-        match entry {
-            include_dir::DirEntry::Dir(sub_dir) => {
-                copy_sub_dir(sub_dir, target_base, dry_run, filter)?;
+/// Recursively read layer files as (path relative to `root`, content)
+fn collect_layer_files(root: &Path, dir: &Path, files: &mut Vec<(PathBuf, String)>) -> Result<()> {
+    let entries = fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.file_name().is_some_and(|n| n == ".git") {
+            continue;
+        }
+        if path.is_dir() {
+            collect_layer_files(root, &path, files)?;
+            continue;
+        }
+
+        let Ok(relative_path) = path.strip_prefix(root) else {
+            continue;
+        };
+        match fs::read(&path).map(String::from_utf8) {
+            Ok(Ok(content)) => files.push((relative_path.to_path_buf(), content)),
+            Ok(Err(_)) => {
+                println!("{} {} (not UTF-8, skipped)", "⚠".yellow(), path.display());
             }
-            include_dir::DirEntry::File(file) => {
-                copy_file_entry(file, target_base, dry_run, filter)?;
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to read {}", path.display()));
             }
         }
     }
     Ok(())
 }
 
+// ============================================================================
+// Template Copying (with filter)
+// ============================================================================
+
+/// State shared while copying a template tree
+struct CopyContext<'a> {
+    target_base: &'a Path,
+    dry_run: bool,
+    filter: &'a InstallFilter,
+    lock: &'a mut InstallLock,
+    vars: &'a TemplateVars,
+    profiles: &'a ActiveProfiles,
+    merge_strategy: Option<MergeStrategy>,
+    summary: MergeSummary,
+}
 
-/// Copy a subdirectory entry from embedded templates
-fn copy_sub_dir(sub_dir: &Dir, target_base: &Path, dry_run: bool, filter: &InstallFilter) -> Result<()> {
-    let relative_path = sub_dir.path();
+/// Per-install settings for `copy_all_templates_filtered`
+pub struct CopyOptions<'a> {
+    pub dry_run: bool,
+    pub vars: &'a TemplateVars,
+    pub profiles: &'a ActiveProfiles,
+    /// How JSON configs with local changes are merged (`--merge-strategy`)
+    pub merge_strategy: Option<MergeStrategy>,
+}
 
-    if should_skip(relative_path, &filter.skip) {
-        let msg = if dry_run { "Would skip directory:" } else { "Skipped directory:" };
-        println!("{} {}", msg.yellow(), relative_path.display());
-        return Ok(());
+/// Files that needed more than a plain write during a template copy
+#[derive(Default)]
+struct MergeSummary {
+    /// Local modifications kept (template unchanged since install)
+    kept: Vec<String>,
+    /// Local modifications merged cleanly with template changes
+    merged: Vec<String>,
+    /// Merged with conflict markers (path, number of conflicts)
+    conflicts: Vec<(String, usize)>,
+    /// Non-mergeable files whose local modifications were backed up and replaced
+    replaced: Vec<String>,
+    /// Files without an install base rebuilt from the template with their hagi:keep blocks
+    carried: Vec<String>,
+    /// Local versions kept because the merge base is missing (template in `<file>.hagi-new`)
+    unmerged: Vec<String>,
+}
+
+impl MergeSummary {
+    fn is_empty(&self) -> bool {
+        self.kept.is_empty() && self.merged.is_empty() && self.conflicts.is_empty() && self.replaced.is_empty()
+            && self.carried.is_empty()
+            && self.unmerged.is_empty()
     }
 
-    // When --only is active, skip entire directories that don't match any target category
-    if filter.has_only() && !dir_has_matching_files(sub_dir, filter) {
-        return Ok(());
+    fn print(&self, dry_run: bool) {
+        if self.is_empty() {
+            return;
+        }
+
+        println!("\n{}", "Locally modified templates:".yellow().bold());
+        for path in &self.kept {
+            println!("  {} {} (template unchanged, local version kept)", "=".blue(), path);
+        }
+        for path in &self.merged {
+            println!("  {} {} (merged cleanly)", "✓".green(), path);
+        }
+        for (path, count) in &self.conflicts {
+            println!("  {} {} ({} conflict(s))", "✗".red(), path, count);
+        }
+        for path in &self.replaced {
+            println!("  {} {} (not mergeable, local version backed up)", "⚠".yellow(), path);
+        }
+        for path in &self.carried {
+            println!("  {} {} (hagi:keep blocks carried over, other local edits backed up)", "⚠".yellow(), path);
+        }
+        for path in &self.unmerged {
+            println!(
+                "  {} {} (no merge base, local version kept; merge {}{} by hand)",
+                "⚠".yellow(),
+                path,
+                path,
+                NEW_FILE_SUFFIX
+            );
+        }
+
+        if !self.conflicts.is_empty() && !dry_run {
+            println!(
+                "\n{}",
+                "Resolve the <<<<<<< local / >>>>>>> template markers in the files above.".yellow()
+            );
+        }
     }
+}
 
-    let target_subdir = target_base.join(relative_path);
-    if dry_run {
-        println!("{} {}", "Would create:".yellow(), target_subdir.display());
-    } else {
-        utils::ensure_dir(&target_subdir)?;
+/// Suffix of the template written next to a local file that could not be merged
+const NEW_FILE_SUFFIX: &str = ".hagi-new";
+
+/// How an installed file relates to its install base and the current template
+enum FileState {
+    /// Not on disk yet
+    Missing,
+    /// Identical to the current template
+    UpToDate,
+    /// Unmodified since install; the template changed
+    Untouched,
+    /// Modified locally; the template is unchanged since install
+    ModifiedLocally,
+    /// Modified locally and the template changed (needs a three-way merge)
+    Diverged,
+    /// On disk, differs from the template, but no install record exists
+    Unrecorded,
+}
+
+/// Classify an existing file against the install lock and the current template
+fn classify_file(existing: Option<&str>, template: &str, base_hash: Option<&str>) -> FileState {
+    let Some(existing) = existing else {
+        return FileState::Missing;
+    };
+    if existing == template {
+        return FileState::UpToDate;
     }
+    let Some(base_hash) = base_hash else {
+        return FileState::Unrecorded;
+    };
 
-    copy_dir_recursive(sub_dir, target_base, dry_run, filter)
+    let locally_modified = lock::content_hash(existing.as_bytes()) != base_hash;
+    let upstream_changed = lock::content_hash(template.as_bytes()) != base_hash;
+    match (locally_modified, upstream_changed) {
+        (false, _) => FileState::Untouched,
+        (true, false) => FileState::ModifiedLocally,
+        (true, true) => FileState::Diverged,
+    }
 }
 
-/// Check if any file in this directory (recursively) matches the filter
-fn dir_has_matching_files(dir: &Dir, filter: &InstallFilter) -> bool {
-    for entry in dir.entries() {
-        match entry {
-            include_dir::DirEntry::File(file) => {
-                if filter.should_include(file.path()) {
-                    return true;
-                }
+/// Whether a template can be three-way merged line by line
+fn is_mergeable(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("md" | "markdown" | "txt")
+    )
+}
+
+/// Copy templates to target directory with category filter
+///
+/// Templates are rendered with `opts.vars`, adjusted for the active `opts.profiles` and
+/// every written file is recorded in `lock`. Files modified since the last install are
+/// kept or three-way merged instead of being overwritten; `hagi:keep` blocks of text
+/// files are carried over by name (see `keep::apply`).
+pub fn copy_all_templates_filtered(
+    target_dir: &Path,
+    templates: &TemplateSet,
+    filter: &InstallFilter,
+    lock: &mut InstallLock,
+    opts: &CopyOptions,
+) -> Result<()> {
+    let mut ctx = CopyContext {
+        target_base: target_dir,
+        dry_run: opts.dry_run,
+        filter,
+        lock,
+        vars: opts.vars,
+        profiles: opts.profiles,
+        merge_strategy: opts.merge_strategy,
+        summary: MergeSummary::default(),
+    };
+    copy_template_set(templates, &mut ctx)?;
+    ctx.summary.print(opts.dry_run);
+    Ok(())
+}
+
+/// Copy every file of a template set that passes the filter
+fn copy_template_set(templates: &TemplateSet, ctx: &mut CopyContext) -> Result<()> {
+    let mut reported_skips: Vec<&str> = Vec::new();
+
+    for (relative_path, file) in &templates.files {
+        if !ctx.filter.should_include(relative_path) {
+            if let Some(skip) = ctx.filter.skip.iter().find(|s| matches_skip(relative_path, s))
+                && !reported_skips.contains(&skip.as_str())
+            {
+                reported_skips.push(skip);
+                let msg = if ctx.dry_run { "Would skip:" } else { "Skipped:" };
+                println!("{} {}", msg.yellow(), skip);
             }
-            include_dir::DirEntry::Dir(sub_dir) => {
-                if dir_has_matching_files(sub_dir, filter) {
-                    return true;
+            continue;
+        }
+
+        copy_file_entry(relative_path, file, ctx)?;
+    }
+    Ok(())
+}
+
+/// Render and install a single template file
+fn copy_file_entry(relative_path: &Path, file: &TemplateFile, ctx: &mut CopyContext) -> Result<()> {
+    let raw = file.content.as_str();
+    let content = render::render_file(relative_path, raw, ctx.vars)
+        .with_context(|| format!("Failed to render template: {}", relative_path.display()))?;
+    let content = ctx.profiles.apply(relative_path, content)?;
+
+    if ctx.dry_run && content != raw {
+        print_rendered(relative_path, &content);
+    }
+    if ctx.dry_run && file.origin != TemplateOrigin::Embedded {
+        println!("{} {} ({})", "From:".cyan(), relative_path.display(), file.origin);
+    }
+
+    install_template_file(relative_path, &content, ctx)
+}
+
+/// Show rendered template output (dry run)
+fn print_rendered(relative_path: &Path, content: &str) {
+    println!("{} {}", "Rendered:".cyan(), relative_path.display());
+    for line in content.lines() {
+        println!("  {} {}", "│".dimmed(), line.dimmed());
+    }
+}
+
+/// Install one template file, respecting local modifications
+fn install_template_file(relative_path: &Path, template: &str, ctx: &mut CopyContext) -> Result<()> {
+    let target_file = ctx.target_base.join(relative_path);
+    let display_path = relative_path.display().to_string();
+    let category = Category::for_path(relative_path);
+
+    let existing = if target_file.exists() {
+        Some(fs::read_to_string(&target_file).with_context(|| {
+            format!("Failed to read existing file: {}", target_file.display())
+        })?)
+    } else {
+        None
+    };
+    let base_hash = ctx.lock.get(relative_path).map(|e| e.base_hash().to_string());
+
+    match classify_file(existing.as_deref(), template, base_hash.as_deref()) {
+        FileState::UpToDate => {
+            println!("{} {}", "Up to date:".dimmed(), target_file.display());
+            if !ctx.dry_run {
+                ctx.lock.record(relative_path, category, template.as_bytes());
+                lock::store_base(ctx.target_base, relative_path, template)?;
+            }
+        }
+        FileState::ModifiedLocally => {
+            let msg = if ctx.dry_run { "Would keep:" } else { "Kept:" };
+            println!("{} {} (modified locally)", msg.blue(), target_file.display());
+            ctx.summary.kept.push(display_path);
+        }
+        FileState::Missing | FileState::Untouched => {
+            write_template_file(&target_file, template, ctx.dry_run, false)?;
+            if !ctx.dry_run {
+                ctx.lock.record(relative_path, category, template.as_bytes());
+                lock::store_base(ctx.target_base, relative_path, template)?;
+            }
+        }
+        FileState::Diverged | FileState::Unrecorded if is_json_config(relative_path) => {
+            let local = existing.unwrap_or_default();
+            let base = base_hash.and_then(|hash| lock::load_base(ctx.target_base, relative_path, &hash));
+            merge_json_config(relative_path, &local, template, base.as_deref(), ctx)?;
+        }
+        FileState::Unrecorded if is_mergeable(relative_path) && existing.as_deref().is_some_and(keep::has_blocks) => {
+            // No base to merge against: rebuild from the template, keeping the blocks
+            let applied = keep::apply(template, &existing.unwrap_or_default());
+            print_orphaned(relative_path, &applied.orphaned);
+            if ctx.dry_run {
+                println!("{} {} (preserved blocks kept)", "Would update:".yellow(), target_file.display());
+            } else {
+                utils::backup_file(&target_file)?;
+                utils::cleanup_old_backups(&target_file, utils::DEFAULT_MAX_BACKUPS)?;
+                fs::write(&target_file, &applied.text)
+                    .with_context(|| format!("Failed to write {}", target_file.display()))?;
+                ctx.lock.record_with_base(relative_path, category, applied.text.as_bytes(), template.as_bytes());
+                lock::store_base(ctx.target_base, relative_path, template)?;
+                println!("{} {} (preserved blocks kept)", "Updated:".green(), target_file.display());
+            }
+            ctx.summary.carried.push(display_path);
+        }
+        FileState::Unrecorded => {
+            // Installed before the install lock existed: keep the legacy backup + overwrite
+            write_template_file(&target_file, template, ctx.dry_run, true)?;
+            if !ctx.dry_run {
+                ctx.lock.record(relative_path, category, template.as_bytes());
+                lock::store_base(ctx.target_base, relative_path, template)?;
+            }
+        }
+        FileState::Diverged if !is_mergeable(relative_path) => {
+            write_template_file(&target_file, template, ctx.dry_run, true)?;
+            if !ctx.dry_run {
+                ctx.lock.record(relative_path, category, template.as_bytes());
+                lock::store_base(ctx.target_base, relative_path, template)?;
+            }
+            ctx.summary.replaced.push(display_path);
+        }
+        FileState::Diverged => {
+            let local = existing.unwrap_or_default();
+            let Some(base) = base_hash.and_then(|hash| lock::load_base(ctx.target_base, relative_path, &hash)) else {
+                // Merging against an empty base would turn the whole file into one conflict
+                let new_file = new_file_path(&target_file);
+                let text = keep::apply(template, &local).text;
+                if ctx.dry_run {
+                    println!("{} {}", "Would write:".yellow(), new_file.display());
+                } else {
+                    fs::write(&new_file, &text)
+                        .with_context(|| format!("Failed to write {}", new_file.display()))?;
+                    println!(
+                        "{} {} has no merge base; kept it and wrote the template to {}",
+                        "⚠".yellow(),
+                        target_file.display(),
+                        new_file.display()
+                    );
                 }
+                ctx.summary.unmerged.push(display_path);
+                return Ok(());
+            };
+            let (result, orphaned) = keep::merge3(&base, &local, template);
+            print_orphaned(relative_path, &orphaned);
+
+            if ctx.dry_run {
+                println!("{} {} (three-way merge)", "Would merge:".yellow(), target_file.display());
+            } else {
+                utils::backup_file(&target_file)?;
+                utils::cleanup_old_backups(&target_file, utils::DEFAULT_MAX_BACKUPS)?;
+                fs::write(&target_file, &result.text).with_context(|| {
+                    format!("Failed to write merged file: {}", target_file.display())
+                })?;
+                ctx.lock.record_with_base(relative_path, category, result.text.as_bytes(), template.as_bytes());
+                lock::store_base(ctx.target_base, relative_path, template)?;
+                println!("{} {} (three-way merge)", "Merged:".green(), target_file.display());
+            }
+
+            if result.conflicts > 0 {
+                ctx.summary.conflicts.push((display_path, result.conflicts));
+            } else {
+                ctx.summary.merged.push(display_path);
             }
         }
     }
-    false
+
+    Ok(())
 }
 
-/// Copy a file entry from embedded templates
-fn copy_file_entry(file: &include_dir::File, target_base: &Path, dry_run: bool, filter: &InstallFilter) -> Result<()> {
-    let relative_path = file.path();
+/// Warn about preserved blocks moved to the orphaned section
+fn print_orphaned(relative_path: &Path, orphaned: &[String]) {
+    for name in orphaned {
+        println!(
+            "{} {}: the template no longer has block '{}'; moved it to the orphaned section",
+            "⚠".yellow(),
+            relative_path.display(),
+            name
+        );
+    }
+}
 
-    if !filter.should_include(relative_path) {
-        if should_skip(relative_path, &filter.skip) {
-            let msg = if dry_run { "Would skip file:" } else { "Skipped file:" };
-            println!("{} {}", msg.yellow(), relative_path.display());
+/// Where the template goes when a locally modified file cannot be merged (`<file>.hagi-new`)
+pub fn new_file_path(target_file: &Path) -> PathBuf {
+    let mut name = target_file.as_os_str().to_owned();
+    name.push(NEW_FILE_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Whether a template is a JSON config merged key by key (`mcp.json`, `settings.local.json`)
+fn is_json_config(path: &Path) -> bool {
+    Category::for_path(path) == Some(Category::Config) && path.extension().is_some_and(|e| e == "json")
+}
+
+/// Merge a config template into a locally modified file, keeping user keys (see `json_merge::merge`)
+fn merge_json_config(
+    relative_path: &Path,
+    local: &str,
+    template: &str,
+    base: Option<&str>,
+    ctx: &mut CopyContext,
+) -> Result<()> {
+    let target_file = ctx.target_base.join(relative_path);
+    let template_value: serde_json::Value = serde_json::from_str(template)
+        .with_context(|| format!("Failed to parse template: {}", relative_path.display()))?;
+    let Ok(local_value) = serde_json::from_str::<serde_json::Value>(local) else {
+        // Not valid JSON: nothing to merge into, fall back to backup + overwrite
+        println!("{} {} is not valid JSON; replacing it", "⚠".yellow(), target_file.display());
+        write_template_file(&target_file, template, ctx.dry_run, true)?;
+        if !ctx.dry_run {
+            ctx.lock.record(relative_path, Category::for_path(relative_path), template.as_bytes());
+            lock::store_base(ctx.target_base, relative_path, template)?;
         }
+        ctx.summary.replaced.push(relative_path.display().to_string());
         return Ok(());
+    };
+    let base_value = base.and_then(|b| serde_json::from_str::<serde_json::Value>(b).ok());
+
+    let (merged, changes) = json_merge::merge(&local_value, &template_value, base_value.as_ref(), ctx.merge_strategy);
+    let mut text = serde_json::to_string_pretty(&merged).context("Failed to serialize merged JSON")?;
+    text.push('\n');
+
+    let msg = if ctx.dry_run { "Would merge:" } else { "Merged:" };
+    println!("{} {} (JSON keys)", msg.green(), target_file.display());
+    json_merge::print_changes(&changes);
+
+    if !ctx.dry_run {
+        if merged != local_value {
+            utils::backup_file(&target_file)?;
+            utils::cleanup_old_backups(&target_file, utils::DEFAULT_MAX_BACKUPS)?;
+            fs::write(&target_file, &text)
+                .with_context(|| format!("Failed to write merged file: {}", target_file.display()))?;
+        }
+        let written = if merged != local_value { text.as_str() } else { local };
+        ctx.lock
+            .record_with_base(relative_path, Category::for_path(relative_path), written.as_bytes(), template.as_bytes());
+        lock::store_base(ctx.target_base, relative_path, template)?;
     }
+    ctx.summary.merged.push(relative_path.display().to_string());
+    Ok(())
+}
 
-    let target_file = target_base.join(relative_path);
-    let content = file
-        .contents_utf8()
-        .context("Template file is not valid UTF-8")?;
-
+/// Write template content to disk, optionally backing up the existing file first
+fn write_template_file(target_file: &Path, content: &str, dry_run: bool, backup: bool) -> Result<()> {
     if dry_run {
         let action = if target_file.exists() { "Would overwrite:" } else { "Would create:" };
         println!("{} {}", action.yellow(), target_file.display());
         return Ok(());
     }
 
-    // Backup existing file
-    if target_file.exists() {
-        utils::backup_file(&target_file)?;
-        utils::cleanup_old_backups(&target_file, utils::DEFAULT_MAX_BACKUPS)?;
+    if backup && target_file.exists() {
+        utils::backup_file(target_file)?;
+        utils::cleanup_old_backups(target_file, utils::DEFAULT_MAX_BACKUPS)?;
     }
 
     // Ensure parent directory exists
@@ -254,7 +774,7 @@ fn copy_file_entry(file: &include_dir::File, target_base: &Path, dry_run: bool,
         utils::ensure_dir(parent)?;
     }
 
-    fs::write(&target_file, content).with_context(|| {
+    fs::write(target_file, content).with_context(|| {
         format!("Failed to write template file: {}", target_file.display())
     })?;
 
@@ -265,60 +785,56 @@ fn copy_file_entry(file: &include_dir::File, target_base: &Path, dry_run: bool,
 
 /// Check if a path should be skipped based on skip_paths
 fn should_skip(path: &Path, skip_paths: &[String]) -> bool {
-    for skip in skip_paths {
-        let skip_path = Path::new(skip);
-
-        // Match exact file name or directory name
-        if path == skip_path {
-            return true;
-        }
+    skip_paths.iter().any(|skip| matches_skip(path, skip))
+}
 
-        // Match if path starts with skip_path (directory prefix)
-        if path.starts_with(skip_path) {
-            return true;
-        }
+/// Check if a path matches a single skip entry
+fn matches_skip(path: &Path, skip: &str) -> bool {
+    let skip_path = Path::new(skip);
 
-        // Match file name only (for convenience)
-        if let Some(file_name) = path.file_name()
-            && file_name.to_string_lossy() == skip.as_str()
-        {
-            return true;
-        }
+    // Match exact file name or directory name, or a directory prefix
+    if path == skip_path || path.starts_with(skip_path) {
+        return true;
     }
-    false
+
+    // Match file name only (for convenience)
+    path.file_name().is_some_and(|file_name| file_name.to_string_lossy() == skip)
 }
 
 // ============================================================================
-// CLAUDE.md Update (preserve project section)
+// CLAUDE.md Update (preserve hagi:keep blocks)
 // ============================================================================
 
-const PROJECT_START: &str = "<!-- hagi:project:start -->";
-const PROJECT_END: &str = "<!-- hagi:project:end -->";
-
-/// Extract project section with byte range from CLAUDE.md
-fn extract_project_section(content: &str) -> Option<(usize, usize, &str)> {
-    let start = content.find(PROJECT_START)?;
-    let end = content.find(PROJECT_END)?;
-    if start < end {
-        let end_pos = end + PROJECT_END.len();
-        Some((start, end_pos, &content[start..end_pos]))
-    } else {
-        None
-    }
-}
-
-/// Update CLAUDE.md: preserve project section, replace everything else from template
+/// Update CLAUDE.md: carry its `hagi:keep` blocks, replace everything else from template
 ///
-/// Returns true if updated, false if no markers found.
-pub fn update_claude_md(claude_md_path: &Path, dry_run: bool) -> Result<bool> {
-    let template = get_template(CLAUDE_MD)?;
+/// Returns true if updated, false if the file has no blocks and `adopt` is off.
+/// With `adopt`, the custom content of an unmarked CLAUDE.md is wrapped into a block.
+/// The template is rendered with `vars`; the written content is recorded in `lock`
+/// under `CLAUDE.md`.
+pub fn update_claude_md(
+    claude_md_path: &Path,
+    templates: &TemplateSet,
+    dry_run: bool,
+    lock: &mut InstallLock,
+    vars: &TemplateVars,
+    adopt: bool,
+) -> Result<bool> {
+    let raw = templates.get(CLAUDE_MD)?;
+    let template = render::render(raw, vars)
+        .with_context(|| format!("Failed to render template: {}", CLAUDE_MD))?;
+    let template = template.as_str();
+
+    if dry_run && template != raw {
+        print_rendered(Path::new(CLAUDE_MD), template);
+    }
 
     if !claude_md_path.exists() {
         if dry_run {
             println!("{} {}", "Would create:".yellow(), claude_md_path.display());
         } else {
-            fs::write(claude_md_path, &template)
+            fs::write(claude_md_path, template)
                 .with_context(|| format!("Failed to write {}", claude_md_path.display()))?;
+            lock.record(Path::new(CLAUDE_MD), Some(Category::Docs), template.as_bytes());
             println!("{} {}", "Created:".green(), claude_md_path.display());
         }
         return Ok(true);
@@ -327,43 +843,42 @@ pub fn update_claude_md(claude_md_path: &Path, dry_run: bool) -> Result<bool> {
     let existing = fs::read_to_string(claude_md_path)
         .with_context(|| format!("Failed to read {}", claude_md_path.display()))?;
 
-    // Extract project section from existing file
-    let project_section = match extract_project_section(&existing) {
-        Some((_, _, section)) => section.to_string(),
-        None => {
-            println!(
-                "{} CLAUDE.md has no project section markers. Skipping update.",
-                "⚠".yellow()
-            );
-            return Ok(false);
-        }
-    };
-
-    // Replace project section placeholder in template with existing project content
-    let new_content = match extract_project_section(&template) {
-        Some((start, end, _)) => {
-            format!("{}{}{}", &template[..start], project_section, &template[end..])
-        }
-        None => return Ok(false),
+    let (new_content, preserved) = if keep::has_blocks(&existing) {
+        let applied = keep::apply(template, &existing);
+        print_orphaned(Path::new(CLAUDE_MD), &applied.orphaned);
+        (applied.text, "preserved blocks kept".to_string())
+    } else if existing == template {
+        (existing.clone(), String::new())
+    } else if adopt {
+        let (text, block) = keep::adopt(template, &existing);
+        (text, format!("custom content adopted into block '{}'", block))
+    } else {
+        println!("{} CLAUDE.md has no hagi:keep blocks. Skipping update.", "⚠".yellow());
+        println!("  Run with {} to wrap its custom content into a preserved block", "--adopt".yellow());
+        return Ok(false);
     };
 
     if existing == new_content {
         println!("{} CLAUDE.md already up to date", "✓".green());
+        if !dry_run {
+            lock.record(Path::new(CLAUDE_MD), Some(Category::Docs), new_content.as_bytes());
+        }
         return Ok(true);
     }
 
     if dry_run {
-        println!("{} CLAUDE.md (preserving project section)", "Would update:".yellow());
+        println!("{} CLAUDE.md ({})", "Would update:".yellow(), preserved);
         return Ok(true);
     }
 
     utils::backup_file(claude_md_path)?;
     utils::cleanup_old_backups(claude_md_path, utils::DEFAULT_MAX_BACKUPS)?;
 
-    fs::write(claude_md_path, new_content)
+    fs::write(claude_md_path, &new_content)
         .with_context(|| format!("Failed to write {}", claude_md_path.display()))?;
+    lock.record(Path::new(CLAUDE_MD), Some(Category::Docs), new_content.as_bytes());
 
-    println!("{} CLAUDE.md (project section preserved)", "Updated:".green());
+    println!("{} CLAUDE.md ({})", "Updated:".green(), preserved);
     Ok(true)
 }
 
@@ -372,7 +887,88 @@ pub fn update_claude_md(claude_md_path: &Path, dry_run: bool) -> Result<bool> {
 // ============================================================================
 
 /// Copy chat templates to target directory
-pub fn copy_chat_templates(target_dir: &Path, dry_run: bool) -> Result<()> {
+pub fn copy_chat_templates(target_dir: &Path, dry_run: bool, lock: &mut InstallLock) -> Result<()> {
     let filter = InstallFilter::default();
-    copy_dir_recursive(&CHAT_TEMPLATES, target_dir, dry_run, &filter)
+    let templates = TemplateSet::from_embedded(&CHAT_TEMPLATES);
+    // Chat mode has no project to derive variables from
+    let vars = TemplateVars::default();
+    let profiles = ActiveProfiles::none();
+    let mut ctx = CopyContext {
+        target_base: target_dir,
+        dry_run,
+        filter: &filter,
+        lock,
+        vars: &vars,
+        profiles: &profiles,
+        merge_strategy: None,
+        summary: MergeSummary::default(),
+    };
+    copy_template_set(&templates, &mut ctx)?;
+    ctx.summary.print(dry_run);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_global_set_layers_packs() {
+        let pack = tempfile::tempdir().unwrap();
+        for (path, content) in [
+            ("agents/reviewer.md", "reviewer"),
+            ("instructions/team.md", "team"),
+            ("hooks/team.sh", "#!/bin/sh\n"),
+            ("CLAUDE.md", "# Team project guidelines\n"),
+            ("settings.local.json", "{\"permissions\": {}}"),
+            ("mcp.json", "{}"),
+        ] {
+            let path = pack.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let mut set = TemplateSet::embedded();
+        set.layer_dir(pack.path(), TemplateOrigin::Pack("team".to_string())).unwrap();
+        let global = set.into_global();
+
+        let agents = InstallFilter::new(vec![Category::Agents], Vec::new());
+        let files: Vec<&Path> = global.files(&agents).into_iter().map(|(path, _)| path).collect();
+        assert_eq!(files, vec![Path::new("agents/reviewer.md")]);
+        assert_eq!(global.get("instructions/team.md").unwrap(), "team");
+        // Project-only files and the project CLAUDE.md do not reach ~/.claude
+        assert!(global.get("hooks/team.sh").is_err());
+        assert!(global.get("mcp.json").is_err());
+        assert_eq!(global.get(SETTINGS_TEMPLATE).unwrap(), "{\"permissions\": {}}");
+        assert!(global.get("TODO.md").is_err());
+        assert!(global.get(CLAUDE_MD).unwrap().contains("<!-- hagi:keep:personal:start -->"));
+    }
+
+    /// Install a one-file template set (`instructions/rules.md`) into `target`
+    fn install_rules(target: &Path, lock: &mut InstallLock, template: &str) {
+        let layer = tempfile::tempdir().unwrap();
+        fs::create_dir_all(layer.path().join("instructions")).unwrap();
+        fs::write(layer.path().join("instructions/rules.md"), template).unwrap();
+        let mut set = TemplateSet::default();
+        set.layer_dir(layer.path(), TemplateOrigin::Overlay).unwrap();
+
+        let vars = TemplateVars::default();
+        let profiles = ActiveProfiles::none();
+        let opts = CopyOptions { dry_run: false, vars: &vars, profiles: &profiles, merge_strategy: None };
+        copy_all_templates_filtered(target, &set, &InstallFilter::default(), lock, &opts).unwrap();
+    }
+
+    #[test]
+    fn test_missing_merge_base_keeps_local_version() {
+        let target = tempfile::tempdir().unwrap();
+        let rules = target.path().join("instructions/rules.md");
+        let mut lock = InstallLock::load(target.path()).unwrap();
+        install_rules(target.path(), &mut lock, "a\nb\n");
+
+        fs::write(&rules, "a\nb\nmine\n").unwrap();
+        fs::remove_dir_all(target.path().join(lock::BASE_DIR)).unwrap();
+        install_rules(target.path(), &mut lock, "a\nB\n");
+
+        assert_eq!(fs::read_to_string(&rules).unwrap(), "a\nb\nmine\n");
+        assert_eq!(fs::read_to_string(new_file_path(&rules)).unwrap(), "a\nB\n");
+    }
 }
@@ -1,10 +1,12 @@
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use colored::*;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+use crate::json_merge::{self, Contribution, MergeStrategy};
+
 /// Maximum number of backup files to keep (default)
 pub const DEFAULT_MAX_BACKUPS: usize = 1;
 
@@ -20,6 +22,22 @@ pub fn claude_dir() -> Result<PathBuf> {
     Ok(home_dir()?.join(".claude"))
 }
 
+/// Get the hagi configuration directory (`$XDG_CONFIG_HOME/hagi`, default `~/.config/hagi`)
+pub fn hagi_config_dir() -> Result<PathBuf> {
+    match std::env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir).join("hagi")),
+        None => Ok(home_dir()?.join(".config/hagi")),
+    }
+}
+
+/// Get the hagi cache directory (`$XDG_CACHE_HOME/hagi`, default `~/.cache/hagi`)
+pub fn hagi_cache_dir() -> Result<PathBuf> {
+    match std::env::var_os("XDG_CACHE_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir).join("hagi")),
+        None => Ok(home_dir()?.join(".cache/hagi")),
+    }
+}
+
 /// Get the ~/.local/share/claude/ directory path
 #[expect(dead_code)]
 pub fn claude_data_dir() -> Result<PathBuf> {
@@ -113,7 +131,7 @@ pub fn cleanup_old_backups(original_path: &Path, max_backups: usize) -> Result<(
     }
 
     // Sort by modification time (newest first)
-    backup_files.sort_by(|a, b| b.1.cmp(&a.1));
+    backup_files.sort_by_key(|b| std::cmp::Reverse(b.1));
 
     // Remove old backups beyond max_backups
     let to_remove: Vec<_> = backup_files
@@ -140,40 +158,6 @@ pub fn cleanup_old_backups(original_path: &Path, max_backups: usize) -> Result<(
     Ok(())
 }
 
-/// Merge two JSON values recursively (overlay takes precedence for conflicts)
-///
-/// # Behavior
-/// - Objects are merged recursively
-/// - Arrays and primitives in overlay replace base values
-/// - New keys in overlay are added to base
-///
-/// # Examples
-/// - Base: {"a": 1, "b": {"c": 2}}
-/// - Overlay: {"b": {"c": 3, "d": 4}, "e": 5}
-/// - Result: {"a": 1, "b": {"c": 3, "d": 4}, "e": 5}
-pub fn merge_json(
-    base: &mut serde_json::Value,
-    overlay: &serde_json::Value,
-) -> Result<()> {
-    if let (Some(base_obj), Some(overlay_obj)) = (base.as_object_mut(), overlay.as_object()) {
-        for (key, value) in overlay_obj {
-            if let Some(base_value) = base_obj.get_mut(key) {
-                // If both are objects, merge recursively
-                if base_value.is_object() && value.is_object() {
-                    merge_json(base_value, value)?;
-                } else {
-                    // Otherwise, overlay value replaces base value
-                    *base_value = value.clone();
-                }
-            } else {
-                // New key from overlay - add to base
-                base_obj.insert(key.clone(), value.clone());
-            }
-        }
-    }
-    Ok(())
-}
-
 /// Read JSON file with detailed error reporting
 pub fn read_json_file(path: &Path) -> Result<serde_json::Value> {
     let content = fs::read_to_string(path)
@@ -207,8 +191,15 @@ pub fn write_json_file(path: &Path, value: &serde_json::Value) -> Result<()> {
 }
 
 /// Merge JSON configuration files (preserving existing configuration)
-pub fn merge_json_file(target_path: &Path, new_content: &serde_json::Value) -> Result<()> {
-    let mut base = if target_path.exists() {
+///
+/// Prints a per-key report and returns the merged value that was written, along with
+/// what the new content contributed to it.
+pub fn merge_json_file(
+    target_path: &Path,
+    new_content: &serde_json::Value,
+    strategy: Option<MergeStrategy>,
+) -> Result<(serde_json::Value, Vec<Contribution>)> {
+    let existing = if target_path.exists() {
         println!("{} {}", "Merging into existing:".blue(), target_path.display());
         backup_file(target_path)?;
         // Clean up old backups after creating new one
@@ -220,17 +211,16 @@ pub fn merge_json_file(target_path: &Path, new_content: &serde_json::Value) -> R
         serde_json::json!({})
     };
 
-    merge_json(&mut base, new_content)
-        .with_context(|| format!("Failed to merge JSON configurations for: {}", target_path.display()))?;
+    let (merged, changes, contributions) = json_merge::merge_recorded(&existing, new_content, None, strategy);
+    json_merge::print_changes(&changes);
 
-    write_json_file(target_path, &base)
+    write_json_file(target_path, &merged)
         .with_context(|| format!("Failed to write merged JSON to: {}", target_path.display()))?;
 
-    Ok(())
+    Ok((merged, contributions))
 }
 
 /// Prompt user for confirmation
-#[expect(dead_code)]
 pub fn confirm(message: &str) -> Result<bool> {
     print!("{} [Y/n]: ", message.yellow());
     io::stdout().flush()?;
@@ -257,40 +247,6 @@ pub fn copy_file(from: &Path, to: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Add lines to .gitignore if they don't exist
-pub fn update_gitignore(project_dir: &Path, entries: &[&str]) -> Result<()> {
-    let gitignore_path = project_dir.join(".gitignore");
-
-    let mut content = if gitignore_path.exists() {
-        fs::read_to_string(&gitignore_path)
-            .context("Failed to read .gitignore")?
-    } else {
-        String::new()
-    };
-
-    let mut added = Vec::new();
-    for entry in entries {
-        if !content.lines().any(|line| line.trim() == *entry) {
-            content.push_str(&format!("\n{}", entry));
-            added.push(*entry);
-        }
-    }
-
-    if !added.is_empty() {
-        fs::write(&gitignore_path, content)
-            .context("Failed to write .gitignore")?;
-
-        println!("{} {}", "Updated:".green(), gitignore_path.display());
-        for entry in added {
-            println!("  {} {}", "+".green(), entry);
-        }
-    } else {
-        println!("{} {} (no changes needed)", "Checked:".blue(), gitignore_path.display());
-    }
-
-    Ok(())
-}
-
 /// Expand shell-like environment variable syntax to absolute paths
 ///
 /// Supports the following patterns:
@@ -395,34 +351,66 @@ pub fn get_repository_name() -> String {
         .unwrap_or_else(|| "myproject".to_string())
 }
 
+/// Get the URL of the `origin` remote, if any
+pub fn get_remote_url() -> Option<String> {
+    let output = std::process::Command::new("git")
+        .args(["remote", "get-url", "origin"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let url = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!url.is_empty()).then_some(url)
+}
+
+/// Get the default branch name
+///
+/// Resolution order: `origin/HEAD`, `init.defaultBranch`, then "main".
+pub fn get_default_branch() -> String {
+    let git_stdout = |args: &[&str]| git_output(Path::new("."), args);
+
+    git_stdout(&["symbolic-ref", "--short", "refs/remotes/origin/HEAD"])
+        .map(|head| head.trim_start_matches("origin/").to_string())
+        .or_else(|| git_stdout(&["config", "--get", "init.defaultBranch"]))
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// Run git in `dir` and return trimmed stdout (an error carries git's stderr)
+pub fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")?;
+
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Run git in `dir`; None if it fails or prints nothing
+pub fn git_output(dir: &Path, args: &[&str]) -> Option<String> {
+    git(dir, args).ok().filter(|s| !s.is_empty())
+}
+
+/// Create a git repository in `dir` with a commit identity and one empty commit (tests)
 #[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_merge_json() {
-        let mut base = serde_json::json!({
-            "a": 1,
-            "b": {
-                "c": 2,
-                "d": 3
-            }
-        });
-
-        let overlay = serde_json::json!({
-            "b": {
-                "c": 20,
-                "e": 4
-            },
-            "f": 5
-        });
-
-        merge_json(&mut base, &overlay).unwrap();
-
-        assert_eq!(base["a"], 1);
-        assert_eq!(base["b"]["c"], 20);
-        assert_eq!(base["b"]["d"], 3);
-        assert_eq!(base["b"]["e"], 4);
-        assert_eq!(base["f"], 5);
+pub fn init_test_repo(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+    for args in [
+        &["init", "-q", "-b", "main"][..],
+        &["config", "user.name", "t"],
+        &["config", "user.email", "t@t"],
+        &["commit", "-q", "--allow-empty", "-m", "Initial commit"],
+    ] {
+        git(dir, args).unwrap();
     }
 }
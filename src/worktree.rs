@@ -0,0 +1,148 @@
+use anyhow::{bail, Context, Result};
+use colored::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::utils;
+
+/// Where the project sits among the repository's worktrees
+#[derive(Debug, Clone)]
+pub struct Worktree {
+    /// Main worktree (the first entry of `git worktree list`)
+    pub main_dir: PathBuf,
+    /// The project is a linked worktree (`git worktree add`), not the main one
+    pub linked: bool,
+}
+
+impl Worktree {
+    /// Inspect the repository; None outside a git repository or in a bare one
+    pub fn detect(project_dir: &Path) -> Option<Self> {
+        let list = utils::git_output(project_dir, &["worktree", "list", "--porcelain"])?;
+        let main_dir = list
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("worktree "))
+            .map(PathBuf::from)?;
+        let git_dir = utils::git_output(project_dir, &["rev-parse", "--absolute-git-dir"])?;
+        let common_dir = utils::git_output(project_dir, &["rev-parse", "--git-common-dir"])?;
+        let linked = canonical(&project_dir.join(git_dir)) != canonical(&project_dir.join(common_dir));
+        Some(Self { main_dir, linked })
+    }
+
+    /// `.claude/` of the main worktree
+    pub fn shared_claude_dir(&self) -> PathBuf {
+        self.main_dir.join(".claude")
+    }
+}
+
+/// Target of `.claude` when it is a symlink to another worktree's `.claude/`
+pub fn shared_target(project_dir: &Path) -> Option<PathBuf> {
+    let claude = project_dir.join(".claude");
+    claude.is_symlink().then(|| fs::read_link(&claude).ok()).flatten()
+}
+
+/// Point `.claude` in a linked worktree at the main worktree's `.claude/` (`--shared`)
+///
+/// The main worktree must already be installed. An existing `.claude/` directory is
+/// never replaced.
+pub fn link_shared(project_dir: &Path, dry_run: bool) -> Result<()> {
+    let Some(worktree) = Worktree::detect(project_dir) else {
+        bail!("--shared needs a git repository");
+    };
+    if !worktree.linked {
+        println!(
+            "{} Main worktree: .claude/ here is shared with linked worktrees installed with --shared",
+            "ℹ".blue()
+        );
+        return Ok(());
+    }
+
+    let target = worktree.shared_claude_dir();
+    let link = project_dir.join(".claude");
+    if shared_target(project_dir).is_some_and(|t| t == target) {
+        println!("{} .claude → {} (shared)", "✓".green(), target.display());
+        return Ok(());
+    }
+    if !target.is_dir() {
+        bail!(
+            "{} does not exist; run 'hagi install' in the main worktree ({}) first",
+            target.display(),
+            worktree.main_dir.display()
+        );
+    }
+    if link.is_symlink() || link.exists() {
+        bail!(
+            "{} already exists; move it away to share {} instead",
+            link.display(),
+            target.display()
+        );
+    }
+
+    if dry_run {
+        println!("{} .claude → {}", "Would link:".yellow(), target.display());
+        return Ok(());
+    }
+    create_dir_symlink(&target, &link)?;
+    println!("{} .claude → {}", "✅ Linked:".green(), target.display());
+    Ok(())
+}
+
+/// Suggest `--shared` when a linked worktree has no `.claude/` but the main worktree does
+pub fn print_shared_hint(project_dir: &Path) {
+    let Some(worktree) = Worktree::detect(project_dir) else {
+        return;
+    };
+    if worktree.linked && !project_dir.join(".claude").exists() && worktree.shared_claude_dir().is_dir() {
+        println!(
+            "{} This is a linked worktree; run {} to use {} instead of a separate copy",
+            "ℹ".blue(),
+            "hagi install --shared".yellow(),
+            worktree.shared_claude_dir().display()
+        );
+    }
+}
+
+#[cfg(unix)]
+fn create_dir_symlink(target: &Path, link: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(target, link)
+        .with_context(|| format!("Failed to create symlink {}", link.display()))
+}
+
+#[cfg(not(unix))]
+fn create_dir_symlink(_target: &Path, _link: &Path) -> Result<()> {
+    bail!("--shared is not supported on this platform")
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_linked_worktree_shares_claude_dir() {
+        let tmp = tempfile::tempdir().unwrap();
+        let main = tmp.path().join("main");
+        let linked = tmp.path().join("linked");
+        utils::init_test_repo(&main);
+        utils::git(&main, &["worktree", "add", "-q", linked.to_str().unwrap()]).unwrap();
+        fs::create_dir(main.join(".claude")).unwrap();
+
+        let worktree = Worktree::detect(&main).unwrap();
+        assert!(!worktree.linked);
+        let worktree = Worktree::detect(&linked).unwrap();
+        assert!(worktree.linked);
+        assert_eq!(canonical(&worktree.main_dir), canonical(&main));
+
+        link_shared(&linked, true).unwrap();
+        assert!(shared_target(&linked).is_none());
+        link_shared(&linked, false).unwrap();
+        let target = shared_target(&linked).unwrap();
+        assert_eq!(canonical(&target), canonical(&main.join(".claude")));
+        // Linking again is a no-op
+        link_shared(&linked, false).unwrap();
+    }
+}